@@ -1,9 +1,152 @@
-use pinochle::ai::Bot;
-use pinochle::{Action, Game, Phase};
+use pinochle::ai::{
+    parse_puzzles, run_puzzles, Bot, BotConfig, BotProfile, Difficulty, PlayerView, Tournament,
+};
+use pinochle::{Action, Game};
 use rand::rngs::StdRng;
-use rand::SeedableRng;
+use rand::{thread_rng, SeedableRng};
+use std::iter::Peekable;
+use std::time::Duration;
+
+const USAGE: &str = "usage:
+  pinochle-cli [--iterations <n>] [--time-limit-ms <n>]
+  pinochle-cli tournament <difficulty> <difficulty> [--boards <n>] [--seed <n>] [--threads <n>]
+      [--iterations <n>] [--time-limit-ms <n>]
+  pinochle-cli puzzles [<file>] [--seed <n>] [--iterations <n>] [--time-limit-ms <n>]";
+
+/// The search budget given by `--iterations <n>` and `--time-limit-ms <n>`.
+#[derive(Debug, Default)]
+struct Budget {
+    iterations: Option<usize>,
+    time_limit: Option<Duration>,
+}
+
+impl Budget {
+    /// Records `flag` if it's one of the budget's, returning whether it was.
+    fn parse(&mut self, flag: &str, value: u64) -> bool {
+        match flag {
+            "--iterations" => self.iterations = Some(value as usize),
+            "--time-limit-ms" => self.time_limit = Some(Duration::from_millis(value)),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Sets `config`'s budget, whatever order the flags came in. A time
+    /// limit without `--iterations` lifts the iteration cap, so the search
+    /// uses all of its time.
+    fn apply(&self, config: &mut BotConfig) {
+        if self.time_limit.is_some() {
+            config.time_limit = self.time_limit;
+            config.max_iterations = self.iterations;
+        } else if self.iterations.is_some() {
+            config.max_iterations = self.iterations;
+        }
+    }
+}
+
+/// Reads `--flag <n>` pairs, handing each to `apply` until it turns one
+/// down.
+fn parse_flags(args: impl Iterator<Item = String>, mut apply: impl FnMut(&str, u64) -> bool) {
+    let mut args = args;
+    while let Some(flag) = args.next() {
+        let value = args.next().and_then(|value| value.parse().ok());
+        if !value.is_some_and(|value| apply(&flag, value)) {
+            panic!("{USAGE}");
+        }
+    }
+}
+
+fn parse_difficulty(name: Option<String>) -> Difficulty {
+    let name = name.unwrap_or_else(|| panic!("{USAGE}"));
+    name.parse()
+        .unwrap_or_else(|_| panic!("{name:?} isn't a difficulty\n{USAGE}"))
+}
+
+/// Plays one difficulty against another and prints how the first did.
+fn tournament(mut args: impl Iterator<Item = String>) {
+    let difficulties = [parse_difficulty(args.next()), parse_difficulty(args.next())];
+    let mut configs = difficulties.map(|difficulty| {
+        BotProfile {
+            difficulty,
+            ..Default::default()
+        }
+        .config()
+    });
+    let mut tournament = Tournament::default();
+    let mut budget = Budget::default();
+    parse_flags(args, |flag, value| match flag {
+        "--boards" => {
+            tournament.boards = value as usize;
+            true
+        }
+        "--seed" => {
+            tournament.seed = value;
+            true
+        }
+        "--threads" => {
+            tournament.threads = value as usize;
+            true
+        }
+        _ => budget.parse(flag, value),
+    });
+    for config in &mut configs {
+        budget.apply(config);
+        // The boards already keep every thread busy.
+        config.threads = 1;
+    }
+
+    println!(
+        "{:?} against {:?}, {} boards of one hand played twice each",
+        difficulties[0], difficulties[1], tournament.boards
+    );
+    println!("{}", tournament.run([&configs[0], &configs[1]]));
+}
+
+/// Has the bot play the puzzles in a file, or the library's own, and prints
+/// how it did.
+fn puzzles(mut args: Peekable<impl Iterator<Item = String>>) {
+    let text = match args.next_if(|arg| !arg.starts_with("--")) {
+        Some(path) => std::fs::read_to_string(&path)
+            .unwrap_or_else(|error| panic!("couldn't read {path}: {error}")),
+        None => include_str!("../../library/puzzles.txt").to_string(),
+    };
+    let puzzles = parse_puzzles(&text).unwrap_or_else(|error| panic!("{error}"));
+    let mut config = BotConfig {
+        seed: Some(1),
+        ..Default::default()
+    };
+    let mut budget = Budget::default();
+    parse_flags(args, |flag, value| match flag {
+        "--seed" => {
+            config.seed = Some(value);
+            true
+        }
+        _ => budget.parse(flag, value),
+    });
+    budget.apply(&mut config);
+
+    println!("{}", run_puzzles(&puzzles, &config));
+}
 
 fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("tournament") => {
+            args.next();
+            return tournament(args);
+        }
+        Some("puzzles") => {
+            args.next();
+            return puzzles(args);
+        }
+        _ => {}
+    }
+
+    let mut budget = Budget::default();
+    parse_flags(args, |flag, value| budget.parse(flag, value));
+    let mut config = BotConfig::default();
+    budget.apply(&mut config);
+
     let seed: [u8; 32] =
         serde_json::from_str(include_str!("../../saved_games/test/seed.json")).unwrap();
     let actions: Vec<Action> =
@@ -14,41 +157,8 @@ fn main() {
         game.act(action.clone()).unwrap();
     }
     let bot_player = game.current_player();
-
-    let mut game = Game::new(StdRng::from_seed(seed));
-    let mut bot: Option<Bot> = None;
-
-    for action in &actions {
-        if let Phase::Play(playing_phase) = game.phase() {
-            if bot.is_none() {
-                bot = Some(Bot::new(
-                    bot_player,
-                    game.player_hand(bot_player),
-                    playing_phase.clone(),
-                ));
-            }
-            if let Action::Play(card) = action {
-                if let Some(bot) = &mut bot {
-                    let player_hand = game.player_hand(game.current_player());
-
-                    bot.update(
-                        game.current_player(),
-                        player_hand[*card],
-                        playing_phase.trump,
-                        &playing_phase.trick.cards,
-                    );
-                }
-            }
-        }
-        game.act(action.clone()).unwrap();
-    }
-    let chosen_card = &bot.as_ref().unwrap().get_move();
-    let action = Action::Play(
-        game.player_hand(bot_player)
-            .iter()
-            .position(|x| x == chosen_card)
-            .unwrap(),
-    );
+    let view = PlayerView::new(&game, bot_player);
+    let action = Bot::decide(&view, &config, &mut thread_rng()).expect("the bot can act");
 
     game.act(action).unwrap();
 }
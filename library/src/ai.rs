@@ -1,11 +1,41 @@
-use super::{Card, Player, PlayingPhase, Rank, Suit};
+use super::{
+    each_player, is_legal_play, partner, BidOutcome, Card, Player, PlayingPhase, Rank,
+    RoundSummary, Suit,
+};
+use crate::rules::Rules;
 use enum_iterator::all;
 use itertools::Itertools;
+use likelihood::{Evidence, Sampler};
 use ordered_float::NotNan;
 use rand::prelude::SliceRandom;
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, RngCore, SeedableRng};
 use serde::{Deserialize, Serialize};
-use std::iter::repeat;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{self, AtomicUsize};
+use std::time::{Duration, Instant};
+
+mod bidding;
+mod likelihood;
+mod mcts;
+mod passing;
+mod profile;
+mod puzzle;
+mod rollout;
+mod solver;
+mod tournament;
+mod view;
+
+pub use bidding::HandEstimate;
+pub use profile::{BiddingStyle, BotProfile, Difficulty};
+pub use puzzle::{
+    parse_puzzles, run_puzzles, ParsePuzzleError, Puzzle, PuzzleReport, PuzzleResult,
+};
+pub use rollout::{HeuristicRollout, RandomRollout, RolloutPolicy};
+pub use tournament::{BoardResult, Tournament, TournamentReport};
+pub use view::PlayerView;
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 struct PlayerState {
@@ -41,11 +71,89 @@ impl PlayerState {
     }
 }
 
+/// Why the bot can't deal out the cards it hasn't seen: what it was told
+/// about the hands can't all be true.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// More copies of the card were played or seen in hands than the deck
+    /// holds.
+    TooManyCopies(Card),
+    /// The player was seen holding more cards than they have left.
+    TooManyKnownCards {
+        player: Player,
+        known: usize,
+        hand_size: u8,
+    },
+    /// Every player has been ruled out of holding the card, given the
+    /// highest card of its suit each of them could still hold.
+    NoHolder {
+        card: Card,
+        highest_possible: [Option<Rank>; 4],
+    },
+    /// The unseen cards can't be split to fit every hand. `open` is how many
+    /// more cards each player needs, and `candidates` how many of the unseen
+    /// cards each of them could hold.
+    Unsplittable {
+        open: [i32; 4],
+        candidates: [usize; 4],
+    },
+    /// The player played a card with no cards left.
+    EmptyHand(Player),
+    /// The player played a card they can't have played there.
+    UnplayableCard { player: Player, card: Card },
+}
+
+impl Display for Inconsistency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Inconsistency::TooManyCopies(card) => {
+                write!(f, "more than two copies of {card} were seen")
+            }
+            Inconsistency::TooManyKnownCards {
+                player,
+                known,
+                hand_size,
+            } => write!(
+                f,
+                "{player:?} is known to hold {known} cards but has {hand_size} left"
+            ),
+            Inconsistency::NoHolder {
+                card,
+                highest_possible,
+            } => {
+                write!(f, "no player can hold {card}:")?;
+                for (player, highest) in all::<Player>().zip(highest_possible) {
+                    match highest {
+                        Some(rank) => write!(f, " {player:?} holds at most {rank}{},", card.0)?,
+                        None => write!(f, " {player:?} is out of {},", card.0)?,
+                    }
+                }
+                Ok(())
+            }
+            Inconsistency::Unsplittable { open, candidates } => {
+                write!(f, "the unseen cards can't be split among the hands:")?;
+                for ((player, open), candidates) in all::<Player>().zip(open).zip(candidates) {
+                    write!(f, " {player:?} needs {open} and could hold {candidates},")?;
+                }
+                Ok(())
+            }
+            Inconsistency::EmptyHand(player) => {
+                write!(f, "{player:?} played a card with none left")
+            }
+            Inconsistency::UnplayableCard { player, card } => {
+                write!(f, "{player:?} can't have played {card}")
+            }
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 struct State {
     played_cards: Vec<Card>,
     hand_sizes: [u8; 4],
     players: [PlayerState; 4],
+    #[serde(default)]
+    evidence: Evidence,
 }
 
 impl Default for State {
@@ -54,6 +162,7 @@ impl Default for State {
             played_cards: Default::default(),
             hand_sizes: [12; 4],
             players: Default::default(),
+            evidence: Default::default(),
         }
     }
 }
@@ -71,7 +180,7 @@ impl State {
         mut unknown_cards: Vec<Card>,
         mut res: [Vec<Card>; 4],
         rng: &mut R,
-    ) -> [Vec<Card>; 4]
+    ) -> Result<[Vec<Card>; 4], Inconsistency>
     where
         R: Rng + ?Sized,
     {
@@ -83,6 +192,15 @@ impl State {
                 let player_state = &self.player_state(unsafe { player_from_usize(player) });
                 candidacy_table[idx] |= (player_state.could_have_card(*card) as u8) << player;
             }
+            if candidacy_table[idx] == 0 {
+                return Err(Inconsistency::NoHolder {
+                    card: *card,
+                    highest_possible: self
+                        .players
+                        .each_ref()
+                        .map(|player| player.highest_possible(card.0)),
+                });
+            }
         }
 
         fn clear_constrained(
@@ -127,7 +245,7 @@ impl State {
                 for idx in column_order {
                     let bitset = 1 << idx;
                     if hand_sizes[idx] > 0 && old_value & bitset != 0 {
-                        let mut copied_hand_sizes = hand_sizes.clone();
+                        let mut copied_hand_sizes = hand_sizes;
                         copied_hand_sizes[idx] -= 1;
                         // set possible value
                         candidacy_table[my_row] = bitset;
@@ -137,29 +255,35 @@ impl State {
                         }
                     }
                 }
+                // restore it, so the rows tried above aren't taken as placed
+                candidacy_table[my_row] = old_value;
                 false
             } else {
                 true
             }
         }
 
-        assert!(search(
-            &mut candidacy_table,
-            [
-                self.hand_sizes[0] - res[0].len() as u8,
-                self.hand_sizes[1] - res[1].len() as u8,
-                self.hand_sizes[2] - res[2].len() as u8,
-                self.hand_sizes[3] - res[3].len() as u8,
-            ],
-        ));
+        let open = [0, 1, 2, 3].map(|i| self.hand_sizes[i] as i32 - res[i].len() as i32);
+        let candidates = [0, 1, 2, 3].map(|i| {
+            candidacy_table
+                .iter()
+                .filter(|row| *row & (1 << i) != 0)
+                .count()
+        });
+        if open.iter().any(|open| *open < 0)
+            || !search(&mut candidacy_table, open.map(|open| open as u8))
+        {
+            return Err(Inconsistency::Unsplittable { open, candidates });
+        }
 
         for (card, location) in unknown_cards.iter().zip(candidacy_table) {
             res[location.trailing_zeros() as usize].push(*card);
         }
 
-        res
+        Ok(res)
     }
 
+    #[allow(dead_code)]
     fn random_solution<R>(
         &self,
         mut unknown_cards: Vec<Card>,
@@ -171,8 +295,7 @@ impl State {
     {
         unknown_cards.shuffle(rng);
 
-        for i in 0..4 {
-            let new_hand = &mut res[i];
+        for (i, new_hand) in res.iter_mut().enumerate() {
             let player_state = &self.player_state(unsafe { player_from_usize(i) });
 
             let mut cant_have = vec![];
@@ -187,7 +310,7 @@ impl State {
                     }
                 }
             }
-            if cant_have.len() > 0 {
+            if !cant_have.is_empty() {
                 unknown_cards.extend(cant_have);
                 if i < 2 {
                     // if there's more than 1 player left, and we put
@@ -199,25 +322,40 @@ impl State {
         Some(res)
     }
 
-    fn produce_candidate_hands<R>(&self, rng: &mut R) -> [Vec<Card>; 4]
+    /// Deals the unknown cards at random among the hands that could hold
+    /// them.
+    fn produce_consistent_hands<R>(&self, rng: &mut R) -> Result<[Vec<Card>; 4], Inconsistency>
     where
         R: Rng + ?Sized,
     {
+        let (res, unknown_cards) = self.known_hands()?;
+        self.calculate_solution(unknown_cards, res, rng)
+    }
+
+    /// The cards each player is known to hold, and the rest of the cards
+    /// still in play, in order.
+    fn known_hands(&self) -> Result<([Vec<Card>; 4], Vec<Card>), Inconsistency> {
         let mut res = [vec![], vec![], vec![], vec![]];
         let mut known_cards = self.played_cards.clone();
-        for i in 0..4 {
-            let player_cards = &self
-                .player_state(unsafe { player_from_usize(i) })
-                .known_cards;
+        for (i, hand) in res.iter_mut().enumerate() {
+            let player = unsafe { player_from_usize(i) };
+            let player_cards = &self.player_state(player).known_cards;
+            if player_cards.len() > self.hand_sizes[i] as usize {
+                return Err(Inconsistency::TooManyKnownCards {
+                    player,
+                    known: player_cards.len(),
+                    hand_size: self.hand_sizes[i],
+                });
+            }
             known_cards.extend(player_cards);
-            res[i].extend(player_cards)
+            hand.extend(player_cards)
         }
         known_cards.sort();
-        let mut deck = all::<Card>().flat_map(|x| repeat(x).take(2));
+        let mut deck = all::<Card>().flat_map(|x| std::iter::repeat_n(x, 2));
         let mut unknown_cards: Vec<Card> = vec![];
         for card in known_cards {
             loop {
-                let next = deck.next().expect("theres not more cards than we know of");
+                let next = deck.next().ok_or(Inconsistency::TooManyCopies(card))?;
                 if card == next {
                     break;
                 }
@@ -225,17 +363,73 @@ impl State {
             }
         }
         unknown_cards.extend(deck);
+        Ok((res, unknown_cards))
+    }
 
-        self.calculate_solution(unknown_cards, res, rng)
+    /// Records that `player` holds `cards`. Cards already known to be theirs
+    /// aren't counted twice, since seeing the same card again doesn't mean
+    /// they hold the second copy.
+    fn add_known_cards(&mut self, player: Player, cards: &[Card]) {
+        let known_cards = &mut self.player_state_mut(player).known_cards;
+        let mut unmatched = known_cards.clone();
+        for card in cards {
+            match unmatched.iter().position(|c| c == card) {
+                Some(position) => {
+                    unmatched.remove(position);
+                }
+                None => known_cards.push(*card),
+            }
+        }
+    }
+
+    /// Records that `player` no longer holds `cards`, without them having
+    /// been played.
+    fn remove_known_cards(&mut self, player: Player, cards: &[Card]) {
+        let known_cards = &mut self.player_state_mut(player).known_cards;
+        for card in cards {
+            if let Some(position) = known_cards.iter().position(|c| c == card) {
+                known_cards.remove(position);
+            }
+        }
     }
-    fn update(&mut self, player: Player, played: Card, trump: Suit, stack: &[Card]) {
+
+    /// Sets up a position part way through the play: the players hold
+    /// `hands`, and every other card in the two decks has been played.
+    fn deal(&mut self, hands: &[Vec<Card>; 4]) {
+        self.hand_sizes = hands.each_ref().map(|hand| hand.len() as u8);
+        self.played_cards = all::<Card>().flat_map(|card| [card, card]).collect();
+        for card in hands.iter().flatten() {
+            let played = &mut self.played_cards;
+            let position = played.iter().position(|c| c == card);
+            played.remove(position.expect("at most two copies of each card"));
+        }
+    }
+
+    /// Records that `player` played `played`, without inferring anything
+    /// about the rest of their hand.
+    fn record_play(&mut self, player: Player, played: Card) -> Result<(), Inconsistency> {
+        let hand_size = &mut self.hand_sizes[player as usize];
+        *hand_size = hand_size
+            .checked_sub(1)
+            .ok_or(Inconsistency::EmptyHand(player))?;
         self.played_cards.push(played);
-        self.hand_sizes[player as usize] -= 1;
         let player_state = self.player_state_mut(player);
         if let Some(position) = player_state.known_cards.iter().position(|x| x == &played) {
             player_state.known_cards.remove(position);
         }
-        if let Some(lead_card) = stack.get(0) {
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        player: Player,
+        played: Card,
+        trump: Suit,
+        stack: &[Card],
+    ) -> Result<(), Inconsistency> {
+        self.record_play(player, played)?;
+        let player_state = self.player_state_mut(player);
+        if let Some(lead_card) = stack.first() {
             if played.0 != lead_card.0 {
                 player_state.set_highest_possible(lead_card.0, None);
                 if played.0 != trump {
@@ -260,6 +454,7 @@ impl State {
                 }
             }
         }
+        Ok(())
     }
 }
 
@@ -278,7 +473,7 @@ fn test_candidate_hands_exclusive_constraints() {
     state
         .player_state_mut(Player::D)
         .set_highest_possible(Suit::Clubs, None);
-    let res = state.produce_candidate_hands(&mut rng);
+    let res = state.produce_consistent_hands(&mut rng).unwrap();
     assert!(res[2].iter().all(|x| x.0 == Suit::Clubs));
     assert!(res.iter().all(|x| x.len() == 12));
 }
@@ -298,111 +493,677 @@ fn test_candidate_hands_inclusive_constraints() {
     state
         .player_state_mut(Player::C)
         .set_highest_possible(Suit::Diamonds, None);
-    let res = state.produce_candidate_hands(&mut rng);
+    let res = state.produce_consistent_hands(&mut rng).unwrap();
     assert!(res[2].iter().all(|x| x.0 == Suit::Spades));
     assert!(res.iter().all(|x| x.len() == 12));
 }
 
+/// A hand with every card's location decided, so its play can be simulated
+/// to the end.
+#[derive(Clone, Debug)]
+struct Deal {
+    phase: PlayingPhase,
+    current_player: Player,
+    hands: [Vec<Card>; 4],
+}
+
+impl Deal {
+    /// Each distinct card the current player may play.
+    fn legal_moves(&self) -> Vec<Card> {
+        let hand = &self.hands[self.current_player as usize];
+        hand.iter()
+            .copied()
+            .filter(|card| is_legal_play(&self.phase.trick.cards, hand, *card, self.phase.trump))
+            .unique()
+            .collect()
+    }
+
+    /// Plays `card`, which has to be legal, for the current player.
+    fn play(&mut self, card: Card) -> Option<RoundSummary> {
+        let hand = &mut self.hands[self.current_player as usize];
+        let (next_player, summary) = self
+            .phase
+            .play(self.current_player, hand, card)
+            .expect("the card is legal to play");
+        hand.remove(hand.iter().position(|c| *c == card).unwrap());
+        self.current_player = next_player;
+        summary
+    }
+
+    /// Plays the card `policy` picks for the current player.
+    fn step(
+        &mut self,
+        policy: &dyn RolloutPolicy,
+        rng: &mut dyn RngCore,
+    ) -> Option<(Card, Option<RoundSummary>)> {
+        let legal = self.legal_moves();
+        if legal.is_empty() {
+            return None;
+        }
+        let partner_hand = &self.hands[partner(self.current_player) as usize];
+        let card =
+            policy.choose_with_partner(&self.phase, self.current_player, partner_hand, &legal, rng);
+        Some((card, self.play(card)))
+    }
+
+    /// Steps until the hand is over.
+    fn finish(
+        &mut self,
+        policy: &dyn RolloutPolicy,
+        rng: &mut dyn RngCore,
+    ) -> Option<RoundSummary> {
+        loop {
+            if let (_, Some(summary)) = self.step(policy, rng)? {
+                return Some(summary);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse_hand(cards: &str) -> Vec<Card> {
+    cards
+        .split_whitespace()
+        .map(|card| card.parse().expect("a card such as TH"))
+        .collect()
+}
+
+/// A phase of the play with the cards in `trick` played so far, led by
+/// `first_player`, the bid won by A for nothing and the rest defaulted.
+#[cfg(test)]
+fn test_phase(trump: Suit, first_player: Player, trick: &str) -> PlayingPhase {
+    PlayingPhase {
+        trump,
+        bid_winner: Player::A,
+        highest_bid: 0,
+        extra_points: Default::default(),
+        counters: Default::default(),
+        set_penalty: Default::default(),
+        piles: Default::default(),
+        trick: crate::Trick {
+            first_player,
+            cards: parse_hand(trick),
+        },
+    }
+}
+
+/// How many iterations a search runs when it has no other limit.
+const DEFAULT_ITERATIONS: usize = 10000;
+
+/// How the bot searches for a card to play, and how long it may take.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BotConfig {
+    /// The most deals searched per move.
+    pub max_iterations: Option<usize>,
+    /// How long a move may take. The search stops at whichever limit comes
+    /// first and plays the best card found so far. With neither limit set,
+    /// it runs a default number of iterations.
+    pub time_limit: Option<Duration>,
+    /// The UCB exploration constant. Rewards are scaled so that all the
+    /// counters in a hand are worth 1.
+    pub exploration: f64,
+    /// Once this few cards are left in all the hands together, the bot
+    /// solves sampled deals exactly instead of searching the tree. Setting
+    /// it to 48 solves every move, which gets slow early in the hand.
+    pub solve_below: usize,
+    /// The most deals sampled and solved per move once solving.
+    pub solver_samples: usize,
+    /// Once this few cards are left in all the hands together, the bot
+    /// solves every way the unseen cards could lie instead of a sample of
+    /// them, which finds the card that's best on average over all of them.
+    /// If there are too many ways to go through, it falls back to the above.
+    pub enumerate_below: usize,
+    /// How many threads the search is spread over. A search split into
+    /// fewer `search_chunks` than this only keeps that many of them busy.
+    pub threads: usize,
+    /// How many parts a search is split into, each seeded on its own and
+    /// given an even share of the iterations. A tree search grows a tree
+    /// per part. Without it, there's a part per thread, so a single thread
+    /// grows a single tree.
+    pub search_chunks: Option<usize>,
+    /// Seeds every move's search, so the same position always gets the same
+    /// card. Without a seed, each move draws fresh randomness. A move only
+    /// repeats if the search is bounded by iterations rather than time, and
+    /// is split into the same parts: set `search_chunks` to get the same
+    /// card whatever `threads` is.
+    pub seed: Option<u64>,
+    /// Whether the bot narrows down other hands from what's been revealed,
+    /// passed and played. Without it, the bot only knows its own hand and
+    /// the cards played so far.
+    pub inference: bool,
+    /// How often the bot plays a worse card than its best on purpose.
+    pub mistake_rate: f64,
+    pub bidding: BiddingStyle,
+    /// How many deals are simulated per trump suit when estimating a hand
+    /// for bidding.
+    pub bidding_samples: usize,
+    /// What the bot plays for while its team holds the bid.
+    pub objective: Objective,
+    /// What the bot plays for while defending against the bid.
+    pub defense: Objective,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            max_iterations: Some(DEFAULT_ITERATIONS),
+            time_limit: None,
+            exploration: 0.7,
+            solve_below: 16,
+            solver_samples: 100,
+            enumerate_below: 12,
+            threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            search_chunks: None,
+            seed: None,
+            inference: true,
+            mistake_rate: 0.0,
+            bidding: Default::default(),
+            bidding_samples: 200,
+            objective: Default::default(),
+            defense: Default::default(),
+        }
+    }
+}
+
+impl BotConfig {
+    /// How much `team` values a hand that ended with `summary`, by whichever
+    /// objective fits its side of the bid.
+    fn utility(&self, summary: &RoundSummary, team: usize) -> f64 {
+        if summary.bid_winner as usize % 2 == team {
+            self.objective.utility(summary, team)
+        } else {
+            self.defense.utility(summary, team)
+        }
+    }
+}
+
+/// What the bot tries to get out of a hand.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Objective {
+    /// The team's average score.
+    #[default]
+    Points,
+    /// The chance of the team's side of the bid coming off: making it when
+    /// the team won the bid, and setting the bid winner when it didn't.
+    Contract,
+    /// Points and the contract together, with the contract given this share
+    /// of the weight, from 0 to 1.
+    Blend(f64),
+}
+
+impl Objective {
+    /// Scores are scaled so that all the counters in a hand are worth 1, the
+    /// same as the contract coming off.
+    fn utility(self, summary: &RoundSummary, team: usize) -> f64 {
+        let points = summary.scores[team] as f64 / summary.counters.total() as f64;
+        let bidders = summary.bid_winner as usize % 2 == team;
+        let contract = f64::from(u8::from((summary.outcome == BidOutcome::Made) == bidders));
+        match self {
+            Objective::Points => points,
+            Objective::Contract => contract,
+            Objective::Blend(weight) => (1.0 - weight) * points + weight * contract,
+        }
+    }
+}
+
+/// What's left of a search's allowance of iterations and time.
+struct Budget {
+    start: Instant,
+    max_iterations: usize,
+    time_limit: Option<Duration>,
+}
+
+impl Budget {
+    fn new(max_iterations: Option<usize>, time_limit: Option<Duration>) -> Self {
+        let max_iterations = match (max_iterations, time_limit) {
+            (Some(max_iterations), _) => max_iterations,
+            (None, Some(_)) => usize::MAX,
+            (None, None) => DEFAULT_ITERATIONS,
+        };
+        Self {
+            start: Instant::now(),
+            max_iterations,
+            time_limit,
+        }
+    }
+
+    /// Whether the search should stop after `iterations`. It always gets one
+    /// in, so there's a move to return.
+    fn spent(&self, iterations: usize) -> bool {
+        iterations > 0 && (iterations >= self.max_iterations || self.timed_out())
+    }
+
+    fn timed_out(&self) -> bool {
+        self.time_limit
+            .is_some_and(|limit| self.start.elapsed() >= limit)
+    }
+}
+
+/// How a card fared in the bot's search.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CardAnalysis {
+    pub card: Card,
+    /// The bot's team's average score for the hand after playing the card.
+    pub expected_score: f64,
+    pub variance: f64,
+    /// How many sampled deals the card was played in.
+    pub samples: u32,
+    /// How often the bid winner's team went on to make its bid.
+    pub make_probability: f64,
+    /// The card's average value by the bot's objective, which is what the
+    /// cards are ranked by.
+    pub utility: f64,
+}
+
+/// What a card scored over the deals it was played in. Deals can count for
+/// more than one, when each stands for several equally likely ones.
+#[derive(Debug, Default, Clone, Copy)]
+struct ScoreStats {
+    samples: u32,
+    weight: f64,
+    total: f64,
+    squares: f64,
+    made: f64,
+    utility: f64,
+}
+
+impl ScoreStats {
+    /// Adds a deal that ended with `summary`, scored for `team` and worth
+    /// `utility` to it.
+    fn add(&mut self, summary: &RoundSummary, team: usize, utility: f64) {
+        self.add_weighted(summary, team, utility, 1.0);
+    }
+
+    fn add_weighted(&mut self, summary: &RoundSummary, team: usize, utility: f64, weight: f64) {
+        let score = summary.scores[team] as f64;
+        self.samples += 1;
+        self.weight += weight;
+        self.total += weight * score;
+        self.squares += weight * score * score;
+        if summary.outcome == BidOutcome::Made {
+            self.made += weight;
+        }
+        self.utility += weight * utility;
+    }
+
+    fn merge(&mut self, other: &ScoreStats) {
+        self.samples += other.samples;
+        self.weight += other.weight;
+        self.total += other.total;
+        self.squares += other.squares;
+        self.made += other.made;
+        self.utility += other.utility;
+    }
+
+    /// The total weight, or 1 before any deals are added so that averages
+    /// come out as 0.
+    fn weight(&self) -> f64 {
+        if self.weight > 0.0 {
+            self.weight
+        } else {
+            1.0
+        }
+    }
+
+    fn mean(&self) -> f64 {
+        self.total / self.weight()
+    }
+
+    fn mean_utility(&self) -> f64 {
+        self.utility / self.weight()
+    }
+
+    fn analysis(&self, card: Card) -> CardAnalysis {
+        CardAnalysis {
+            card,
+            expected_score: self.mean(),
+            variance: (self.squares / self.weight() - self.mean().powi(2)).max(0.0),
+            samples: self.samples,
+            make_probability: self.made / self.weight(),
+            utility: self.mean_utility(),
+        }
+    }
+}
+
+/// Adds up what each worker found about each card.
+fn merge_stats(workers: Vec<HashMap<Card, ScoreStats>>) -> HashMap<Card, ScoreStats> {
+    let mut merged: HashMap<Card, ScoreStats> = HashMap::new();
+    for (card, stats) in workers.into_iter().flatten() {
+        merged.entry(card).or_default().merge(&stats);
+    }
+    merged
+}
+
+/// How many deals `Bot::get_move_flat` plays out.
+const FLAT_ROLLOUTS: usize = 30000;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Bot {
     state: State,
     player: Player,
     hand: Vec<Card>,
     playing_phase: PlayingPhase,
+    #[serde(default)]
+    config: BotConfig,
+    /// How the deals the bot simulates are played out past its search.
+    #[serde(skip, default = "default_rollout")]
+    rollout: Box<dyn RolloutPolicy>,
+}
+
+/// The first card in `hand` that may be played to `phase`'s trick.
+fn first_legal(hand: &[Card], phase: &PlayingPhase) -> Option<Card> {
+    let trick = &phase.trick.cards;
+    hand.iter()
+        .copied()
+        .find(|card| is_legal_play(trick, hand, *card, phase.trump))
+}
+
+fn default_rollout() -> Box<dyn RolloutPolicy> {
+    Box::new(HeuristicRollout)
 }
 
 impl Bot {
-    pub fn update(&mut self, player: Player, played: Card, trump: Suit, stack: &[Card]) {
-        self.state.update(player, played, trump, stack);
-        self.playing_phase.play(player, &[], played).unwrap();
-        if player == self.player {
-            self.hand
-                .remove(self.hand.iter().position(|x| *x == played).unwrap());
-        }
-    }
-
-    fn try_random_hand(&self) -> Option<(Card, i32)> {
-        let mut candidate = self.state.produce_candidate_hands(&mut thread_rng());
-
-        for hand in &mut candidate {
-            hand.shuffle(&mut thread_rng());
-        }
-
-        fn step(
-            phase: &mut PlayingPhase,
-            current_player: &mut Player,
-            hands: &mut [Vec<Card>; 4],
-        ) -> Option<(Card, Option<(i32, i32)>)> {
-            let hand = &mut hands[*current_player as usize];
-            if let Some((next_player, points, card)) = hand.iter().find_map(|card| {
-                phase
-                    .play(*current_player, hand, *card)
-                    .ok()
-                    .map(|(n, p)| (n, p, *card))
-            }) {
-                hand.remove(hand.iter().position(|c| *c == card).unwrap());
-                *current_player = next_player;
-                Some((card, points))
-            } else {
-                dbg!("This SHOULD NOT HAVE HAPPENED");
-                None
-            }
+    /// Takes in `player` playing `played` onto `stack`, the trick so far, or
+    /// why it can't have happened.
+    pub fn update(
+        &mut self,
+        player: Player,
+        played: Card,
+        trump: Suit,
+        stack: &[Card],
+    ) -> Result<(), Inconsistency> {
+        let unplayable = || Inconsistency::UnplayableCard {
+            player,
+            card: played,
+        };
+        let own = if player == self.player {
+            let position = self.hand.iter().position(|x| *x == played);
+            Some(position.ok_or_else(unplayable)?)
+        } else {
+            None
+        };
+        if self.config.inference {
+            self.state.update(player, played, trump, stack)?;
+            self.state.observe_play(player, played, &self.playing_phase);
+        } else {
+            self.state.record_play(player, played)?;
+        }
+        self.playing_phase
+            .play(player, &[], played)
+            .map_err(|_| unplayable())?;
+        if let Some(position) = own {
+            self.hand.remove(position);
         }
+        Ok(())
+    }
 
-        fn filter_points(player: Player, (x, y): (i32, i32)) -> i32 {
-            if player as u8 % 2 == 0 {
-                x
-            } else {
-                y
-            }
+    /// Takes in how each player bid, starting with `first_bidder`, where a
+    /// bid of 0 is a pass. The bot reads more into a bid than the cards it
+    /// rules out: deals that fit the bidding are sampled more often.
+    pub fn observe_bidding(&mut self, first_bidder: Player, bids: &[i32]) {
+        if self.config.inference {
+            self.state.observe_bids(first_bidder, bids);
         }
-        let mut current_player = self.player;
-        let mut phase = self.playing_phase.clone();
-        let (first_card, points) = step(&mut phase, &mut current_player, &mut candidate)?;
-        if let Some(points) = points {
-            return Some((first_card, filter_points(self.player, points)));
+    }
+
+    /// Takes in the cards each player showed as meld. Call this before any
+    /// card of the hand is played.
+    pub fn observe_reveals(&mut self, reveals: &[Option<Vec<Card>>; 4]) {
+        if !self.config.inference {
+            return;
         }
-        loop {
-            let (_, points) = step(&mut phase, &mut current_player, &mut candidate)?;
-            if let Some(points) = points {
-                return Some((first_card, filter_points(self.player, points)));
+        for (player, cards) in each_player(Player::A).zip(reveals) {
+            if let (true, Some(cards)) = (player != self.player, cards) {
+                self.state.add_known_cards(player, cards);
             }
         }
     }
 
+    /// Takes in `from` passing `cards` to their partner. Only passes the bot
+    /// was part of tell it anything: cards it sent are known to be with its
+    /// partner, and cards it got back are known not to be. What its partner
+    /// chose to send also hints at what they kept, going by the `rules` it
+    /// was made under.
+    pub fn observe_pass(&mut self, from: Player, cards: &[Card], rules: &Rules) {
+        if !self.config.inference {
+            return;
+        }
+        let to = partner(from);
+        if from == self.player {
+            self.state.add_known_cards(to, cards);
+            self.state.observe_sent(cards);
+        } else if to == self.player {
+            self.state.remove_known_cards(from, cards);
+            self.state.observe_received(from, cards, rules);
+        }
+    }
+
+    fn try_random_hand<R>(&self, sampler: &Sampler, mut rng: &mut R) -> Option<(Card, RoundSummary)>
+    where
+        R: Rng + ?Sized,
+    {
+        let mut deal = Deal {
+            phase: self.playing_phase.clone(),
+            current_player: self.player,
+            hands: sampler.sample(rng).ok()?,
+        };
+        let first_card = *deal.legal_moves().choose(rng)?;
+        let summary = match deal.play(first_card) {
+            Some(summary) => summary,
+            None => deal.finish(self.rollout.as_ref(), &mut rng)?,
+        };
+        Some((first_card, summary))
+    }
+
+    /// Splits a search into `config.search_chunks` chunks, or one per
+    /// thread, each with its own RNG seeded from `rng` and an even share of
+    /// `max_iterations`. Runs them over `config.threads` workers, and
+    /// collects what each chunk found in chunk order, so a seeded search cut
+    /// into the same chunks finds the same card however many threads there
+    /// are. The chunks share the time limit, counted from `start`. Each
+    /// chunk is also told where its share starts among all the iterations.
+    fn run_workers<T, R, W>(
+        &self,
+        start: Instant,
+        max_iterations: Option<usize>,
+        rng: &mut R,
+        work: W,
+    ) -> Vec<T>
+    where
+        T: Send,
+        R: Rng + ?Sized,
+        W: Fn(usize, &mut StdRng, &Budget) -> T + Sync,
+    {
+        let time_limit = self.config.time_limit;
+        let max_iterations = match (max_iterations, time_limit) {
+            (None, None) => Some(DEFAULT_ITERATIONS),
+            (max_iterations, _) => max_iterations,
+        };
+        let chunks = self.config.search_chunks.unwrap_or(self.config.threads);
+        let chunks = match max_iterations {
+            Some(max_iterations) => chunks.clamp(1, max_iterations.max(1)),
+            None => chunks.max(1),
+        };
+        let seeds: Vec<u64> = (0..chunks).map(|_| rng.gen()).collect();
+        let share = |chunk: usize| {
+            max_iterations.map(|max_iterations| {
+                max_iterations / chunks + usize::from(chunk < max_iterations % chunks)
+            })
+        };
+        let next = AtomicUsize::new(0);
+
+        let mut results: Vec<(usize, T)> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.config.threads.clamp(1, chunks))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = vec![];
+                        loop {
+                            let chunk = next.fetch_add(1, atomic::Ordering::Relaxed);
+                            let Some(seed) = seeds.get(chunk) else {
+                                return results;
+                            };
+                            let first = (0..chunk).filter_map(share).sum();
+                            let budget = Budget {
+                                start,
+                                ..Budget::new(share(chunk), time_limit)
+                            };
+                            let mut rng = StdRng::seed_from_u64(*seed);
+                            results.push((chunk, work(first, &mut rng, &budget)));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("search worker panicked"))
+                .collect()
+        });
+        results.sort_by_key(|(chunk, _)| *chunk);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
     pub fn new(player: Player, hand: Vec<Card>, playing_phase: PlayingPhase) -> Self {
+        Self::with_config(player, hand, playing_phase, Default::default())
+    }
+
+    pub fn with_config(
+        player: Player,
+        hand: Vec<Card>,
+        playing_phase: PlayingPhase,
+        config: BotConfig,
+    ) -> Self {
         let mut me = Bot {
             state: Default::default(),
-            player: player,
+            player,
             hand: hand.clone(),
             playing_phase,
+            config,
+            rollout: default_rollout(),
         };
 
         me.state.players[player as usize].known_cards = hand;
+        if config.inference {
+            let phase = &me.playing_phase;
+            me.state.observe_declaration(phase.bid_winner, phase.trump);
+        }
         me
     }
 
+    /// Plays out the bot's simulated deals with `policy` instead of the
+    /// heuristic one.
+    pub fn with_rollout_policy(mut self, policy: impl RolloutPolicy + 'static) -> Self {
+        self.rollout = Box::new(policy);
+        self
+    }
+
     pub fn get_move(&self) -> Card {
-        let groups = (0..30000)
-            .filter_map(|_| self.try_random_hand())
-            .into_group_map();
-        *groups
+        match self.config.seed {
+            Some(seed) => self.get_move_with_rng(&mut StdRng::seed_from_u64(seed)),
+            None => self.get_move_with_rng(&mut thread_rng()),
+        }
+    }
+
+    /// Picks a card with all of the search's randomness drawn from `rng`. If
+    /// what the bot has seen doesn't add up, it plays its first legal card.
+    pub fn get_move_with_rng<R>(&self, rng: &mut R) -> Card
+    where
+        R: Rng + ?Sized,
+    {
+        let analysis = self.analyze_with_rng(rng).unwrap_or_default();
+        if analysis.len() > 1 && rng.gen_bool(self.config.mistake_rate.clamp(0.0, 1.0)) {
+            analysis[rng.gen_range(1..analysis.len())].card
+        } else {
+            analysis
+                .first()
+                .map_or_else(|| self.fallback_move(), |best| best.card)
+        }
+    }
+
+    /// The first legal card in the bot's hand, for when it can't search.
+    fn fallback_move(&self) -> Card {
+        first_legal(&self.hand, &self.playing_phase).expect("the bot has a card to play")
+    }
+
+    /// Every legal card with how it fared in the search, starting with the
+    /// card `get_move` would play, or why the other hands can't be dealt.
+    pub fn analyze(&self) -> Result<Vec<CardAnalysis>, Inconsistency> {
+        match self.config.seed {
+            Some(seed) => self.analyze_with_rng(&mut StdRng::seed_from_u64(seed)),
+            None => self.analyze_with_rng(&mut thread_rng()),
+        }
+    }
+
+    pub fn analyze_with_rng<R>(&self, rng: &mut R) -> Result<Vec<CardAnalysis>, Inconsistency>
+    where
+        R: Rng + ?Sized,
+    {
+        self.state.produce_consistent_hands(rng)?;
+        let cards_left = self
+            .state
+            .hand_sizes
             .iter()
-            .map(|(card, scores)| {
-                (
-                    card,
-                    NotNan::new(scores.iter().sum::<i32>() as f32 / scores.len() as f32).unwrap(),
-                )
+            .map(|size| *size as usize)
+            .sum::<usize>();
+        let start = Instant::now();
+        if cards_left <= self.config.enumerate_below {
+            if let Some(analysis) = self.solve_exhaustive(start, rng)? {
+                return Ok(analysis);
+            }
+        }
+        Ok(if cards_left <= self.config.solve_below {
+            self.solve_sampled(start, rng)
+        } else {
+            self.search(start, rng)
+        })
+    }
+
+    /// Picks a card by playing out random deals from each legal card and
+    /// taking the one that best meets the bot's objective. Kept as a baseline for
+    /// the tree search.
+    pub fn get_move_flat(&self) -> Card {
+        match self.config.seed {
+            Some(seed) => self.get_move_flat_with_rng(&mut StdRng::seed_from_u64(seed)),
+            None => self.get_move_flat_with_rng(&mut thread_rng()),
+        }
+    }
+
+    pub fn get_move_flat_with_rng<R>(&self, rng: &mut R) -> Card
+    where
+        R: Rng + ?Sized,
+    {
+        let team = self.player as usize % 2;
+        let Ok(sampler) = self.state.sampler(Some(FLAT_ROLLOUTS), rng) else {
+            return self.fallback_move();
+        };
+        let workers = self.run_workers(
+            Instant::now(),
+            Some(FLAT_ROLLOUTS),
+            rng,
+            |_, rng, budget| {
+                let mut scores: HashMap<Card, ScoreStats> = HashMap::new();
+                let mut rollouts = 0;
+                while !budget.spent(rollouts) {
+                    rollouts += 1;
+                    if let Some((card, summary)) = self.try_random_hand(&sampler, rng) {
+                        let utility = self.config.utility(&summary, team);
+                        scores.entry(card).or_default().add(&summary, team, utility);
+                    }
+                }
+                scores
+            },
+        );
+        merge_stats(workers)
+            .into_iter()
+            .max_by_key(|(card, scores)| {
+                (NotNan::new(scores.mean_utility()).unwrap(), Reverse(*card))
             })
-            .max_by_key(|(_, score)| *score)
-            .expect("some move exists")
-            .0
+            .map_or_else(|| self.fallback_move(), |(card, _)| card)
     }
 }
 
 #[test]
 fn try_bot() {
-    use crate::Trick;
     use Player::*;
     use Rank::*;
     use Suit::*;
@@ -422,21 +1183,240 @@ fn try_bot() {
         Card(Hearts, Nine),
     ];
 
-    let bot = Bot::new(
+    let bot = Bot::with_config(
         Player::A,
         hand,
-        PlayingPhase {
-            trump: Spades,
-            bid_winner: A,
-            highest_bid: 0,
-            extra_points: Default::default(),
-            piles: Default::default(),
-            trick: Trick {
-                first_player: A,
-                cards: Default::default(),
-            },
+        test_phase(Spades, A, ""),
+        BotConfig {
+            threads: 2,
+            seed: Some(1),
+            ..Default::default()
         },
     );
 
     assert_eq!(bot.get_move(), Card(Hearts, Ace));
+
+    // The same seed gives the same card however many threads search.
+    let mut bot = bot;
+    bot.config.max_iterations = Some(200);
+    bot.config.search_chunks = Some(4);
+    let moves: Vec<_> = [1, 4, 4]
+        .into_iter()
+        .map(|threads| {
+            bot.config.threads = threads;
+            bot.get_move()
+        })
+        .collect();
+    assert_eq!(moves[0], moves[1]);
+    assert_eq!(moves[1], moves[2]);
+
+    let analysis = bot.analyze().unwrap();
+    assert_eq!(analysis[0].card, moves[0]);
+    assert_eq!(analysis.len(), bot.hand.iter().unique().count());
+    assert_eq!(analysis.iter().map(|card| card.samples).sum::<u32>(), 200);
+    assert!(analysis.windows(2).all(|w| w[0].samples >= w[1].samples));
+    assert!(analysis
+        .iter()
+        .all(|card| (0.0..=1.0).contains(&card.make_probability) && card.variance >= 0.0));
+    bot.config.threads = 1;
+    assert_eq!(bot.analyze().unwrap(), analysis);
+}
+
+#[test]
+fn test_inconsistent_observations() {
+    use Player::*;
+
+    let phase = test_phase(Suit::Spades, B, "9D");
+    let config = BotConfig {
+        max_iterations: Some(10),
+        seed: Some(2),
+        ..Default::default()
+    };
+    let hand = parse_hand("AS TS KS QS JS 9S AH KH QH JH AD 9C");
+    let mut bot = Bot::with_config(C, hand.clone(), phase.clone(), config);
+    for player in [A, B, C, D] {
+        bot.state
+            .player_state_mut(player)
+            .set_highest_possible(Suit::Hearts, None);
+    }
+    assert!(matches!(
+        bot.analyze(),
+        Err(Inconsistency::NoHolder {
+            card: Card(Suit::Hearts, _),
+            highest_possible: [None, None, None, None],
+        })
+    ));
+    assert_eq!(bot.get_move(), Card(Suit::Diamonds, Rank::Ace));
+
+    let mut bot = Bot::with_config(C, hand, phase, config);
+    bot.observe_reveals(&[Some(parse_hand("AS AS")), None, None, None]);
+    assert_eq!(
+        bot.analyze(),
+        Err(Inconsistency::TooManyCopies(Card(Suit::Spades, Rank::Ace)))
+    );
+    assert_eq!(bot.get_move(), Card(Suit::Diamonds, Rank::Ace));
+}
+
+#[test]
+fn test_observe_public_cards() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use Player::*;
+
+    let mut rng = StdRng::seed_from_u64(4);
+    let hand = parse_hand("AS AS TS KS QS JS 9S AH AC AD KH QH");
+    let mut bot = Bot::new(
+        A,
+        hand,
+        PlayingPhase {
+            highest_bid: 250,
+            ..test_phase(Suit::Spades, A, "")
+        },
+    );
+
+    bot.observe_pass(C, &parse_hand("AS TS AD AC"), &Default::default());
+    bot.observe_pass(A, &parse_hand("9D 9C JC JH"), &Default::default());
+    bot.observe_pass(B, &parse_hand("9D 9C JC QC"), &Default::default());
+    bot.observe_reveals(&[
+        Some(parse_hand("AS TS KS QS JS")),
+        Some(parse_hand("KD QD")),
+        Some(parse_hand("JC JH JD JS")),
+        None,
+    ]);
+
+    let mut known = bot.state.player_state(C).known_cards.clone();
+    known.sort();
+    assert_eq!(known, parse_hand("9D JD 9C JC JH JS"));
+    assert_eq!(bot.state.player_state(B).known_cards, parse_hand("KD QD"));
+    assert!(bot.state.player_state(D).known_cards.is_empty());
+
+    let sampler = bot.state.sampler(Some(50), &mut rng).unwrap();
+    for _ in 0..50 {
+        let hands = sampler.sample(&mut rng).unwrap();
+        for player in [B, C] {
+            for card in &bot.state.player_state(player).known_cards {
+                assert!(hands[player as usize].contains(card));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_budget() {
+    let budget = Budget::new(Some(3), None);
+    assert!(!budget.spent(0));
+    assert!(!budget.spent(2));
+    assert!(budget.spent(3));
+
+    let budget = Budget::new(None, Some(Duration::ZERO));
+    assert!(!budget.spent(0));
+    assert!(budget.spent(1));
+
+    let budget = Budget::new(None, Some(Duration::from_secs(3600)));
+    assert!(!budget.spent(1_000_000));
+    assert!(Budget::new(None, None).spent(DEFAULT_ITERATIONS));
+}
+
+#[test]
+fn test_run_workers() {
+    let mut bot = Bot::new(
+        Player::A,
+        parse_hand("AS 9H"),
+        test_phase(Suit::Spades, Player::A, ""),
+    );
+    let mut run = |threads, search_chunks| {
+        bot.config.threads = threads;
+        bot.config.search_chunks = search_chunks;
+        bot.run_workers(
+            Instant::now(),
+            Some(10),
+            &mut StdRng::seed_from_u64(7),
+            |first, rng, budget| {
+                let mut iterations = 0;
+                while !budget.spent(iterations) {
+                    iterations += 1;
+                }
+                (first, iterations, rng.gen::<u64>())
+            },
+        )
+    };
+    let shares = |chunks: &[(usize, usize, u64)]| {
+        chunks
+            .iter()
+            .map(|(first, iterations, _)| (*first, *iterations))
+            .collect::<Vec<_>>()
+    };
+    let chunks = run(4, Some(8));
+    assert_eq!(
+        shares(&chunks),
+        [
+            (0, 2),
+            (2, 2),
+            (4, 1),
+            (5, 1),
+            (6, 1),
+            (7, 1),
+            (8, 1),
+            (9, 1)
+        ]
+    );
+    assert_eq!(run(1, Some(8)), chunks);
+    // Left to itself, the search gets a chunk per thread.
+    assert_eq!(shares(&run(4, None)), [(0, 3), (3, 3), (6, 2), (8, 2)]);
+    assert_eq!(shares(&run(1, None)), [(0, 10)]);
+    bot.config.threads = 4;
+    let mut rng = StdRng::seed_from_u64(7);
+    assert_eq!(
+        bot.run_workers(Instant::now(), Some(2), &mut rng, |_, _, _| ())
+            .len(),
+        2
+    );
+}
+
+/// The `feed-partner` puzzle, where C should throw its ten onto a trick its
+/// partner is sure to take.
+#[test]
+fn test_bot_feeds_partner() {
+    let puzzles = parse_puzzles(include_str!("../puzzles.txt")).unwrap();
+    let puzzle = puzzles
+        .iter()
+        .find(|puzzle| puzzle.name == "feed-partner")
+        .unwrap();
+    let config = BotConfig {
+        max_iterations: Some(3000),
+        solve_below: 0,
+        enumerate_below: 0,
+        threads: 1,
+        seed: Some(3),
+        ..Default::default()
+    };
+    assert_eq!(puzzle.bot(config).get_move(), Card(Suit::Hearts, Rank::Ten));
+}
+
+#[test]
+fn test_objective() {
+    use crate::rules::{CounterScheme, SetPenalty};
+
+    // A's team bid 250 and took 200 of the counters, so B's team set it.
+    let summary = RoundSummary {
+        bid_winner: Player::A,
+        highest_bid: 250,
+        counters: CounterScheme::Classic,
+        set_penalty: SetPenalty::default(),
+        outcome: BidOutcome::Set,
+        extra_points: [0; 2],
+        trick_points: [200, 50],
+        scores: [-250, 50],
+    };
+    let mut config = BotConfig::default();
+    assert_eq!(config.utility(&summary, 0), -1.0);
+    assert_eq!(config.utility(&summary, 1), 0.2);
+
+    config.objective = Objective::Contract;
+    config.defense = Objective::Contract;
+    assert_eq!(config.utility(&summary, 0), 0.0);
+    assert_eq!(config.utility(&summary, 1), 1.0);
+
+    config.defense = Objective::Blend(0.5);
+    assert_eq!(config.utility(&summary, 1), 0.6);
 }
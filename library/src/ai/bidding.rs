@@ -0,0 +1,284 @@
+use super::passing::{bid_winner_pass, partner_pass};
+use super::{Bot, BotConfig, Deal, HeuristicRollout, State};
+use crate::rules::Rules;
+use crate::{
+    bonus_points, each_player, has_marriage, partner, Action, Card, Player, PlayingPhase, Suit,
+    Trick,
+};
+use enum_iterator::all;
+use ordered_float::NotNan;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// The lowest bid that can be made, and the step between bids.
+pub(super) const MIN_BID: i32 = 250;
+pub(super) const BID_STEP: i32 = 25;
+/// A partner who bid holds more than an average hand, so they'll likely
+/// meld and pass better than the simulation assumes.
+const PARTNER_BID_BONUS: f32 = 30.0;
+/// An opponent who bid is likely to hold the cards we hoped to win with.
+const OPPONENT_BID_PENALTY: f32 = 10.0;
+/// How much more than the partner's bid the bot needs to think it's worth
+/// before taking the contract away from them.
+const PARTNER_OVERCALL: i32 = 50;
+
+/// What a hand is expected to be worth to its team if its holder wins the
+/// bid and names `trump`, averaged over simulated deals.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HandEstimate {
+    pub trump: Suit,
+    /// Meld in the hand as dealt, before any passing.
+    pub hand_meld: i32,
+    /// Meld shown by the bid winner and their partner after passing.
+    pub meld: f32,
+    /// Points the team takes in tricks.
+    pub trick_points: f32,
+}
+
+impl HandEstimate {
+    pub fn total(&self) -> f32 {
+        self.meld + self.trick_points
+    }
+}
+
+/// Simulates `samples` deals of the cards `player` can't see, passes as the
+/// partnership is expected to, and plays each deal out.
+fn estimate<R>(
+    player: Player,
+    hand: &[Card],
+    trump: Suit,
+    rules: &Rules,
+    samples: usize,
+    mut rng: &mut R,
+) -> HandEstimate
+where
+    R: Rng + ?Sized,
+{
+    let mut state = State::default();
+    state.player_state_mut(player).known_cards = hand.to_vec();
+    let partner = partner(player);
+    let team = player as usize % 2;
+
+    let mut meld = 0;
+    let mut trick_points = 0;
+    let mut played = 0;
+    for _ in 0..samples {
+        // Nothing's known yet beyond the hand, so there's nothing to weigh
+        // the deals by.
+        let Ok(mut hands) = state.produce_consistent_hands(rng) else {
+            continue;
+        };
+        let count = rules.passing.count;
+        if count > 0 {
+            let to_bidder = partner_pass(&hands[partner as usize], trump, count);
+            move_cards(&mut hands, partner, player, &to_bidder);
+            let back = bid_winner_pass(&hands[player as usize], trump, count, rules);
+            move_cards(&mut hands, player, partner, &back);
+        }
+        let team_meld = bonus_points(&hands[player as usize], trump)
+            + bonus_points(&hands[partner as usize], trump);
+
+        let mut deal = Deal {
+            phase: PlayingPhase {
+                trump,
+                bid_winner: player,
+                highest_bid: 0,
+                extra_points: [0; 2],
+                counters: rules.counters,
+                set_penalty: rules.set_penalty,
+                piles: Default::default(),
+                trick: Trick {
+                    first_player: player,
+                    cards: vec![],
+                },
+            },
+            current_player: player,
+            hands,
+        };
+        if let Some(summary) = deal.finish(&HeuristicRollout, &mut rng) {
+            meld += team_meld;
+            trick_points += summary.trick_points[team];
+            played += 1;
+        }
+    }
+
+    let played = played.max(1) as f32;
+    HandEstimate {
+        trump,
+        hand_meld: bonus_points(hand, trump),
+        meld: meld as f32 / played,
+        trick_points: trick_points as f32 / played,
+    }
+}
+
+fn move_cards(hands: &mut [Vec<Card>; 4], from: Player, to: Player, cards: &[Card]) {
+    for card in cards {
+        let hand = &mut hands[from as usize];
+        let card = hand.remove(hand.iter().position(|c| c == card).unwrap());
+        hands[to as usize].push(card);
+    }
+}
+
+impl Bot {
+    /// Estimates what `hand` is worth with each suit `player` could name as
+    /// trump.
+    pub fn evaluate_trumps<R>(
+        player: Player,
+        hand: &[Card],
+        rules: &Rules,
+        config: &BotConfig,
+        rng: &mut R,
+    ) -> Vec<HandEstimate>
+    where
+        R: Rng + ?Sized,
+    {
+        all::<Suit>()
+            .filter(|suit| !rules.trump_requires_marriage || has_marriage(hand, *suit))
+            .map(|trump| estimate(player, hand, trump, rules, config.bidding_samples, rng))
+            .collect()
+    }
+
+    /// Picks the trump suit for `player`, who won the bid, along with the
+    /// estimates the choice was based on. When the rules leave no suit to
+    /// name, the bot throws the hand in and the estimates are empty.
+    pub fn choose_trump<R>(
+        player: Player,
+        hand: &[Card],
+        rules: &Rules,
+        config: &BotConfig,
+        rng: &mut R,
+    ) -> (Action, Vec<HandEstimate>)
+    where
+        R: Rng + ?Sized,
+    {
+        let estimates = Self::evaluate_trumps(player, hand, rules, config, rng);
+        let action = estimates
+            .iter()
+            .max_by_key(|estimate| NotNan::new(estimate.total()).unwrap())
+            .map_or(Action::ThrowIn, |estimate| {
+                Action::DeclareSuit(estimate.trump)
+            });
+        (action, estimates)
+    }
+
+    /// Picks a bid for `player` given the bids made so far, where the first
+    /// was made by `first_bidder`. Zero passes.
+    pub fn choose_bid<R>(
+        player: Player,
+        hand: &[Card],
+        first_bidder: Player,
+        bids: &[i32],
+        rules: &Rules,
+        config: &BotConfig,
+        rng: &mut R,
+    ) -> i32
+    where
+        R: Rng + ?Sized,
+    {
+        let Some(mut value) = Self::evaluate_trumps(player, hand, rules, config, rng)
+            .iter()
+            .map(|estimate| NotNan::new(estimate.total()).unwrap())
+            .max()
+            .map(|value| value.into_inner())
+        else {
+            return 0;
+        };
+
+        let mut highest = (0, None);
+        for (bid, bidder) in bids.iter().zip(each_player(first_bidder)) {
+            if *bid == 0 {
+                continue;
+            }
+            if bidder == partner(player) {
+                value += PARTNER_BID_BONUS;
+            } else {
+                value -= OPPONENT_BID_PENALTY;
+            }
+            if *bid >= highest.0 {
+                highest = (*bid, Some(bidder));
+            }
+        }
+
+        let margin = config.bidding.safety_margin();
+        let bid = ((value - margin) / BID_STEP as f32).floor() as i32 * BID_STEP;
+        let needed = match highest {
+            (amount, Some(bidder)) if bidder == partner(player) => amount + PARTNER_OVERCALL,
+            (amount, Some(_)) => amount + BID_STEP,
+            (_, None) => MIN_BID,
+        };
+        if bid >= needed.max(MIN_BID) {
+            bid
+        } else {
+            0
+        }
+    }
+}
+
+#[test]
+fn test_choose_bid() {
+    use super::parse_hand;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use Player::*;
+
+    let mut rng = StdRng::seed_from_u64(2);
+    let rules = Rules::default();
+    let config = BotConfig::default();
+
+    let strong = parse_hand("AS TS KS QS JS AS TS KS QS JS AH AC");
+    let bid = Bot::choose_bid(A, &strong, A, &[], &rules, &config, &mut rng);
+    assert!(bid >= 1500, "{bid}");
+    assert_eq!(bid % 25, 0);
+
+    let weak = parse_hand("9S 9H 9C 9D JH JC JD 9S QH KC QC TD");
+    assert_eq!(
+        Bot::choose_bid(A, &weak, A, &[], &rules, &config, &mut rng),
+        0
+    );
+
+    let decent = parse_hand("AS TS KS QS JS 9S AH AC AD KH QH JD");
+    let alone = Bot::choose_bid(C, &decent, A, &[0, 0], &rules, &config, &mut rng);
+    assert!(alone >= 250, "{alone}");
+    let outbid = Bot::choose_bid(C, &decent, A, &[0, 2000], &rules, &config, &mut rng);
+    assert_eq!(outbid, 0);
+    let partnered = Bot::choose_bid(C, &decent, A, &[250, 0], &rules, &config, &mut rng);
+    // The partner's bid is worth a little more to the hand, and C must jump
+    // it by the overcall.
+    assert!(partnered >= 250 + PARTNER_OVERCALL, "{partnered}");
+    assert!(
+        (alone..=alone + 2 * BID_STEP).contains(&partnered),
+        "{alone} {partnered}"
+    );
+}
+
+#[test]
+fn test_choose_trump() {
+    use super::parse_hand;
+    use crate::Rank::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use Player::*;
+    use Suit::*;
+
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut rules = Rules::default();
+    let config = BotConfig::default();
+
+    let hand = parse_hand("AH TH KH QH JH 9H AS KS QS AC 9D JC");
+    let (action, estimates) = Bot::choose_trump(B, &hand, &rules, &config, &mut rng);
+    assert!(matches!(action, Action::DeclareSuit(Hearts)));
+    assert_eq!(estimates.len(), 4);
+    let hearts = estimates.iter().find(|e| e.trump == Hearts).unwrap();
+    assert_eq!(hearts.hand_meld, 180);
+    assert!(estimates.iter().all(|e| e.total() <= hearts.total()));
+
+    rules.trump_requires_marriage = true;
+    let hand = parse_hand("AH TH JH 9H AH TH KS QS AC 9D JC QD");
+    let (action, estimates) = Bot::choose_trump(B, &hand, &rules, &config, &mut rng);
+    assert!(matches!(action, Action::DeclareSuit(Spades)));
+    assert_eq!(estimates.len(), 1);
+
+    let hand = vec![Card(Hearts, Ace); 12];
+    let (_, estimates) = Bot::choose_trump(B, &hand, &rules, &config, &mut rng);
+    assert!(estimates.is_empty());
+}
@@ -0,0 +1,327 @@
+use super::bidding::{BID_STEP, MIN_BID};
+use super::passing::{bid_winner_pass, partner_pass};
+use super::{Inconsistency, State};
+use crate::rules::Rules;
+use crate::{
+    bonus_points, compare, each_player, trick_winner, Card, Player, PlayingPhase, Rank, Suit,
+};
+use enum_iterator::all;
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// The most layouts dealt and weighted by how likely each is to have
+/// produced what the other players did, for a search to draw from. It takes
+/// a pool this large for the few layouts that fit the evidence to stand out.
+const POOL_SIZE: usize = 400;
+/// How many layouts go in the pool for each one a short search draws.
+const POOL_PER_DRAW: usize = 4;
+/// How many points either side of its value a hand's bid tends to fall.
+const BID_SPREAD: f64 = 40.0;
+/// How much a hand is guessed to make in tricks before its length in trump
+/// and its aces are counted.
+const BASE_TRICKS: f64 = 100.0;
+/// How likely a player is to throw a counter onto a trick the other team is
+/// taking when they had a card worth less in the same suit.
+const WASTED_COUNTER: f64 = 0.25;
+/// How likely the partner is to pass each card other than the ones the bot
+/// would have passed from the same hand.
+const UNEXPECTED_PASS: f64 = 0.3;
+
+/// What the other players did that says something about their hands, beyond
+/// the cards they can't hold.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub(super) struct Evidence {
+    /// The cards each player has played so far, in order.
+    played: [Vec<Card>; 4],
+    /// Each player's bid, where 0 is a pass, in the order they were made.
+    bids: Vec<(Player, i32)>,
+    /// Who won the bid, and what they made trump.
+    declared: Option<(Player, Suit)>,
+    /// Counters played onto a trick the other team was taking, with how many
+    /// cards the player had played before it.
+    thrown_counters: Vec<(Player, Card, usize)>,
+    /// The cards the bot's partner passed it, and who the partner is.
+    received: Option<(Player, Vec<Card>)>,
+    /// The cards the bot passed its partner.
+    sent: Vec<Card>,
+    /// The rules the hand is played under.
+    rules: Rules,
+}
+
+impl Evidence {
+    fn is_empty(&self) -> bool {
+        self.bids.is_empty()
+            && self.declared.is_none()
+            && self.thrown_counters.is_empty()
+            && self.received.is_none()
+    }
+}
+
+/// A rough guess at what a hand would bid with `trump`: its meld, plus a
+/// share of the tricks that grows with its length in trump and its aces.
+fn hand_value(hand: &[Card], trump: Suit) -> f64 {
+    let trumps = hand.iter().filter(|card| card.0 == trump).count();
+    let aces = hand.iter().filter(|card| card.1 == Rank::Ace).count();
+    bonus_points(hand, trump) as f64 + BASE_TRICKS + 10.0 * (trumps + aces) as f64
+}
+
+/// Deals layouts of the unknown cards, from a weighted pool when there's
+/// evidence to weigh them by.
+pub(super) struct Sampler<'a> {
+    state: &'a State,
+    pool: Option<Pool>,
+}
+
+struct Pool {
+    layouts: Vec<[Vec<Card>; 4]>,
+    weights: WeightedIndex<f64>,
+}
+
+impl Sampler<'_> {
+    pub(super) fn sample<R>(&self, rng: &mut R) -> Result<[Vec<Card>; 4], Inconsistency>
+    where
+        R: Rng + ?Sized,
+    {
+        match &self.pool {
+            Some(pool) => Ok(pool.layouts[pool.weights.sample(rng)].clone()),
+            None => self.state.produce_consistent_hands(rng),
+        }
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+impl State {
+    pub(super) fn observe_bids(&mut self, first_bidder: Player, bids: &[i32]) {
+        self.evidence.bids = each_player(first_bidder)
+            .zip(bids.iter().copied())
+            .collect();
+    }
+
+    pub(super) fn observe_declaration(&mut self, bid_winner: Player, trump: Suit) {
+        self.evidence.declared = Some((bid_winner, trump));
+    }
+
+    pub(super) fn observe_sent(&mut self, cards: &[Card]) {
+        self.evidence.sent = cards.to_vec();
+    }
+
+    pub(super) fn observe_received(&mut self, from: Player, cards: &[Card], rules: &Rules) {
+        self.evidence.received = Some((from, cards.to_vec()));
+        self.evidence.rules = *rules;
+    }
+
+    /// Notes `player` playing `card` to `phase`'s trick, before it's played.
+    pub(super) fn observe_play(&mut self, player: Player, card: Card, phase: &PlayingPhase) {
+        self.evidence.rules.counters = phase.counters;
+        let played = &mut self.evidence.played[player as usize];
+        let trick = &phase.trick.cards;
+        if let Some(lead) = trick.first() {
+            let winner = trick_winner(trick, phase.trump);
+            let partner_winning = winner + 2 == trick.len();
+            if !partner_winning
+                && card.0 == lead.0
+                && compare(card, trick[winner], phase.trump, lead.0).is_le()
+                && phase.counters.points(card.1) > 0
+            {
+                self.evidence
+                    .thrown_counters
+                    .push((player, card, played.len()));
+            }
+        }
+        played.push(card);
+    }
+
+    /// Gets ready to deal the unknown cards `draws` times, or an unknown
+    /// number of times, keeping to what the bot can rule out, and favouring
+    /// layouts that fit the bidding and play. With anything to go on, a pool
+    /// of layouts is dealt up front and weighted by their likelihood,
+    /// normalized across the pool, to be drawn from again.
+    pub(super) fn sampler<R>(
+        &self,
+        draws: Option<usize>,
+        rng: &mut R,
+    ) -> Result<Sampler<'_>, Inconsistency>
+    where
+        R: Rng + ?Sized,
+    {
+        if self.evidence.is_empty() {
+            return Ok(Sampler {
+                state: self,
+                pool: None,
+            });
+        }
+        let size = draws.map_or(POOL_SIZE, |draws| {
+            draws.saturating_mul(POOL_PER_DRAW).clamp(1, POOL_SIZE)
+        });
+        let layouts: Vec<_> = (0..size)
+            .map(|_| self.produce_consistent_hands(rng))
+            .collect::<Result<_, _>>()?;
+        let mut weights: Vec<_> = layouts.iter().map(|hands| self.likelihood(hands)).collect();
+        let total: f64 = weights.iter().sum();
+        for weight in &mut weights {
+            // If nothing fits at all, every layout is as good as any other.
+            *weight = if total > 0.0 { *weight / total } else { 1.0 };
+        }
+        Ok(Sampler {
+            state: self,
+            pool: Some(Pool {
+                layouts,
+                weights: WeightedIndex::new(weights).expect("the pool isn't empty"),
+            }),
+        })
+    }
+
+    /// How likely the players were to bid, pass and play as they did, up to
+    /// a constant, had they been dealt `hands`. Their hands are taken to be
+    /// what they hold now along with what they've played, so passed cards
+    /// the bot didn't see are counted with whoever ended up holding them.
+    /// The bot's own hand is the same in every layout, so it only scales the
+    /// likelihood.
+    pub(super) fn likelihood(&self, hands: &[Vec<Card>; 4]) -> f64 {
+        let evidence = &self.evidence;
+        let mut cache: [Option<[f64; 4]>; 4] = Default::default();
+        let mut values = |player: Player| {
+            *cache[player as usize].get_or_insert_with(|| {
+                let mut hand = hands[player as usize].clone();
+                hand.extend(&evidence.played[player as usize]);
+                let mut values = [0.0; 4];
+                for trump in all::<Suit>() {
+                    values[trump as usize] = hand_value(&hand, trump);
+                }
+                values
+            })
+        };
+        let mut likelihood = 1.0;
+
+        let mut needed = MIN_BID;
+        for (player, bid) in &evidence.bids {
+            if *bid > 0 {
+                needed = bid + BID_STEP;
+            }
+            let value = values(*player).into_iter().fold(f64::MIN, f64::max);
+            likelihood *= if *bid > 0 {
+                sigmoid((value - *bid as f64) / BID_SPREAD)
+            } else {
+                sigmoid((needed as f64 - value) / BID_SPREAD)
+            };
+        }
+
+        if let Some((bid_winner, trump)) = evidence.declared {
+            let weight = |value: f64| (value / BID_SPREAD).exp();
+            let values = values(bid_winner);
+            likelihood *= weight(values[trump as usize]) / values.map(weight).iter().sum::<f64>();
+        }
+
+        // The partner is taken to pass the way the bot would from their hand,
+        // so a pass with no trump says they had little to send.
+        if let (Some((from, passed)), Some((bid_winner, trump))) =
+            (&evidence.received, evidence.declared)
+        {
+            let mut hand = hands[*from as usize].clone();
+            hand.extend(&evidence.played[*from as usize]);
+            hand.extend(passed);
+            let expected = if *from == bid_winner {
+                bid_winner_pass(&hand, trump, passed.len(), &evidence.rules)
+            } else {
+                // What the bot passed back came after, so it wasn't theirs yet.
+                for card in &evidence.sent {
+                    if let Some(position) = hand.iter().position(|c| c == card) {
+                        hand.remove(position);
+                    }
+                }
+                partner_pass(&hand, trump, passed.len())
+            };
+            let mut unexpected = passed.clone();
+            for card in expected {
+                if let Some(position) = unexpected.iter().position(|c| *c == card) {
+                    unexpected.remove(position);
+                }
+            }
+            likelihood *= UNEXPECTED_PASS.powi(unexpected.len() as i32);
+        }
+
+        for (player, card, before) in &evidence.thrown_counters {
+            let mut held = hands[*player as usize].clone();
+            held.extend(&evidence.played[*player as usize][before + 1..]);
+            let points = |rank| evidence.rules.counters.points(rank);
+            if held
+                .iter()
+                .any(|held| held.0 == card.0 && points(held.1) < points(card.1))
+            {
+                likelihood *= WASTED_COUNTER;
+            }
+        }
+        likelihood
+    }
+}
+
+#[test]
+fn test_declaration_favours_trump_length() {
+    use super::parse_hand;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut state = State::default();
+    state.players[0].known_cards = parse_hand("AS TS KS QS JS 9S AD TD KD QD JD 9D");
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut hearts = |state: &State| {
+        let sampler = state.sampler(Some(400), &mut rng).unwrap();
+        (0..400)
+            .map(|_| {
+                let hands = sampler.sample(&mut rng).unwrap();
+                hands[1]
+                    .iter()
+                    .filter(|card| card.0 == Suit::Hearts)
+                    .count()
+            })
+            .sum::<usize>()
+    };
+    let unweighted = hearts(&state);
+    state.observe_declaration(Player::B, Suit::Hearts);
+    let weighted = hearts(&state);
+    // B holds a heart more in every two hands or so.
+    assert!(
+        weighted > unweighted + unweighted / 10,
+        "{weighted} {unweighted}"
+    );
+}
+
+#[test]
+fn test_pass_shows_partner_strength() {
+    use super::parse_hand;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // A won the bid in spades and got three side aces from C, who would
+    // have sent trump first if they had any.
+    let mut state = State::default();
+    state.players[0].known_cards = parse_hand("AS TS KS QS JS 9S AH AC AD KD QD JD 9H 9H 9C");
+    state.hand_sizes = [15, 12, 12, 12];
+    state.observe_declaration(Player::A, Suit::Spades);
+    let mut rng = StdRng::seed_from_u64(4);
+    let mut trump = |state: &State| {
+        let sampler = state.sampler(Some(400), &mut rng).unwrap();
+        (0..400)
+            .map(|_| {
+                let hands = sampler.sample(&mut rng).unwrap();
+                hands[2]
+                    .iter()
+                    .filter(|card| card.0 == Suit::Spades)
+                    .count()
+            })
+            .sum::<usize>()
+    };
+    let unweighted = trump(&state);
+    state.observe_received(Player::C, &parse_hand("AH AC AD"), &Default::default());
+    let weighted = trump(&state);
+    // C is left with about half the trump it would otherwise hold.
+    assert!(
+        weighted < unweighted - unweighted / 3,
+        "{weighted} {unweighted}"
+    );
+}
@@ -0,0 +1,233 @@
+use super::likelihood::Sampler;
+use super::{merge_stats, Bot, CardAnalysis, Deal, ScoreStats};
+use crate::{Card, Player};
+use ordered_float::NotNan;
+use rand::prelude::SliceRandom;
+use rand::Rng;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[derive(Debug)]
+struct Node {
+    /// The card played to reach this node, and who played it.
+    action: Option<(Player, Card)>,
+    children: Vec<usize>,
+    /// The scores of the deals played through this node, for the team of
+    /// the player who played the card.
+    scores: ScoreStats,
+    /// How many times this node's card was legal when its parent was
+    /// visited, which stands in for the parent's visits in UCB since a card
+    /// isn't available in every determinization.
+    availability: u32,
+    /// The total reward to the team of the player who played the card, by
+    /// the objective for its side of the bid.
+    reward: f64,
+}
+
+impl Node {
+    fn new(action: Option<(Player, Card)>) -> Self {
+        Self {
+            action,
+            children: vec![],
+            scores: Default::default(),
+            availability: 0,
+            reward: 0.0,
+        }
+    }
+
+    fn card(&self) -> Card {
+        self.action.expect("only the root has no card").1
+    }
+
+    fn ucb(&self, exploration: f64) -> NotNan<f64> {
+        let visits = self.scores.samples as f64;
+        let value =
+            self.reward / visits + exploration * ((self.availability as f64).ln() / visits).sqrt();
+        NotNan::new(value).unwrap()
+    }
+}
+
+/// A single-observer information set search tree. Every node stands for the
+/// cards played so far, whatever the hands held, so each iteration samples a
+/// deal consistent with what the bot knows and only walks the branches that
+/// deal allows.
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    fn new() -> Self {
+        Self {
+            nodes: vec![Node::new(None)],
+        }
+    }
+
+    fn iterate<R>(&mut self, bot: &Bot, sampler: &Sampler, exploration: f64, mut rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        let Ok(hands) = sampler.sample(rng) else {
+            return;
+        };
+        let mut deal = Deal {
+            phase: bot.playing_phase.clone(),
+            current_player: bot.player,
+            hands,
+        };
+
+        let mut path = vec![0];
+        let mut node = 0;
+        let summary = loop {
+            let player = deal.current_player;
+            let legal = deal.legal_moves();
+            let children = self.nodes[node].children.clone();
+            let mut tried = vec![];
+            for child in children {
+                if legal.contains(&self.nodes[child].card()) {
+                    self.nodes[child].availability += 1;
+                    tried.push(child);
+                }
+            }
+
+            let untried: Vec<_> = legal
+                .iter()
+                .filter(|card| !tried.iter().any(|c| self.nodes[*c].card() == **card))
+                .collect();
+            if let Some(card) = untried.choose(rng) {
+                let mut child = Node::new(Some((player, **card)));
+                child.availability = 1;
+                self.nodes.push(child);
+                let child = self.nodes.len() - 1;
+                self.nodes[node].children.push(child);
+                path.push(child);
+                break deal.play(**card);
+            }
+
+            let Some(child) = tried
+                .into_iter()
+                .max_by_key(|child| self.nodes[*child].ucb(exploration))
+            else {
+                break None;
+            };
+            path.push(child);
+            node = child;
+            if let Some(summary) = deal.play(self.nodes[child].card()) {
+                break Some(summary);
+            }
+        };
+        let Some(summary) = summary.or_else(|| deal.finish(bot.rollout.as_ref(), &mut rng)) else {
+            return;
+        };
+
+        for node in path {
+            let node = &mut self.nodes[node];
+            if let Some((player, _)) = node.action {
+                let team = player as usize % 2;
+                let utility = bot.config.utility(&summary, team);
+                node.scores.add(&summary, team, utility);
+                node.reward += utility;
+            }
+        }
+    }
+
+    /// How each card at the root scored.
+    fn root_scores(&self) -> HashMap<Card, ScoreStats> {
+        self.nodes[0]
+            .children
+            .iter()
+            .map(|child| (self.nodes[*child].card(), self.nodes[*child].scores))
+            .collect()
+    }
+}
+
+impl Bot {
+    /// Analyzes each card with an information set Monte Carlo tree search.
+    /// Each chunk grows its own tree, and the cards are ordered by how often
+    /// they were visited across all the trees, most first. The time limit is
+    /// counted from `start`.
+    pub(super) fn search<R>(&self, start: Instant, rng: &mut R) -> Vec<CardAnalysis>
+    where
+        R: Rng + ?Sized,
+    {
+        let Ok(sampler) = self.state.sampler(self.config.max_iterations, rng) else {
+            return vec![];
+        };
+        let trees = self.run_workers(start, self.config.max_iterations, rng, |_, rng, budget| {
+            let mut tree = Tree::new();
+            let mut iterations = 0;
+            while !budget.spent(iterations) {
+                tree.iterate(self, &sampler, self.config.exploration, rng);
+                iterations += 1;
+            }
+            tree.root_scores()
+        });
+        let mut analysis: Vec<_> = merge_stats(trees)
+            .into_iter()
+            .map(|(card, scores)| scores.analysis(card))
+            .collect();
+        analysis.sort_by_key(|card| (Reverse(card.samples), card.card));
+        analysis
+    }
+}
+
+/// Plays random deals with one team searching the tree and the other using
+/// flat rollouts, swapping seats on each deal. Run with
+/// `cargo test --release -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn bench_against_flat_rollouts() {
+    use super::test_phase;
+    use crate::Suit;
+    use enum_iterator::all;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(5);
+    let mut totals = [0; 2];
+    for deal in 0..20 {
+        let mut deck: Vec<Card> = all::<Card>().flat_map(|c| [c, c]).collect();
+        deck.shuffle(&mut rng);
+        let hands: Vec<Vec<Card>> = deck.chunks(12).map(<[Card]>::to_vec).collect();
+        let phase = test_phase(
+            *all::<Suit>().collect::<Vec<_>>().choose(&mut rng).unwrap(),
+            Player::A,
+            "",
+        );
+        for searching_team in 0..2 {
+            let mut hands = hands.clone();
+            let mut phase = phase.clone();
+            let mut bots: Vec<_> = all::<Player>()
+                .map(|p| Bot::new(p, hands[p as usize].clone(), phase.clone()))
+                .collect();
+            let mut player = Player::A;
+            let scores = loop {
+                let bot = &bots[player as usize];
+                let card = if player as usize % 2 == searching_team {
+                    bot.get_move()
+                } else {
+                    bot.get_move_flat()
+                };
+                let stack = phase.trick.cards.clone();
+                for bot in &mut bots {
+                    bot.update(player, card, phase.trump, &stack).unwrap();
+                }
+                let hand = &mut hands[player as usize];
+                let (next, summary) = phase.play(player, hand, card).unwrap();
+                hand.remove(hand.iter().position(|c| *c == card).unwrap());
+                player = next;
+                if let Some(summary) = summary {
+                    break summary.scores;
+                }
+            };
+            totals[0] += scores[searching_team];
+            totals[1] += scores[1 - searching_team];
+        }
+        println!(
+            "after {} deals: search {} flat {}",
+            deal + 1,
+            totals[0],
+            totals[1]
+        );
+    }
+}
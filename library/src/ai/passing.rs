@@ -0,0 +1,161 @@
+use super::Bot;
+use crate::rules::Rules;
+use crate::{meld_cards, Action, Card, Rank, Suit};
+use enum_iterator::all;
+use std::cmp::Reverse;
+
+/// Flags each of `hand`'s cards that is needed to show its meld.
+fn in_meld(hand: &[Card], trump: Suit) -> Vec<bool> {
+    let mut meld = meld_cards(hand, trump);
+    hand.iter()
+        .map(|card| match meld.iter().position(|c| c == card) {
+            Some(position) => {
+                meld.remove(position);
+                true
+            }
+            None => false,
+        })
+        .collect()
+}
+
+/// Orders `hand` by `value` and takes the `count` most valuable cards.
+fn most_valuable(hand: &[Card], count: usize, value: impl Fn(usize, Card) -> i32) -> Vec<Card> {
+    let mut cards: Vec<_> = hand
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (value(i, *c), *c))
+        .collect();
+    cards.sort_by_key(|(value, card)| Reverse((*value, *card)));
+    cards
+        .into_iter()
+        .take(count)
+        .map(|(_, card)| card)
+        .collect()
+}
+
+/// The partner sends the bid winner their trump, best first, then their
+/// aces, then the pinochle cards that could complete the bid winner's meld.
+/// Off-suit cards the partner needs for their own meld are kept back.
+pub(super) fn partner_pass(hand: &[Card], trump: Suit, count: usize) -> Vec<Card> {
+    let meld = in_meld(hand, trump);
+    most_valuable(hand, count, |i, card| {
+        let mut value = card.1 as i32;
+        if card.0 == trump {
+            value += 100;
+        } else if card.1 == Rank::Ace {
+            value += 60;
+        } else if meld[i] {
+            value -= 40;
+        } else if card == Card(Suit::Spades, Rank::Queen)
+            || card == Card(Suit::Diamonds, Rank::Jack)
+        {
+            value += 20;
+        }
+        value
+    })
+}
+
+/// The bid winner keeps their meld, trump, aces and counters, and sends back
+/// whatever is left, preferring cards from short suits so they can trump
+/// those suits later.
+pub(super) fn bid_winner_pass(
+    hand: &[Card],
+    trump: Suit,
+    count: usize,
+    rules: &Rules,
+) -> Vec<Card> {
+    let meld = in_meld(hand, trump);
+    let suit_lengths = all::<Suit>()
+        .map(|suit| hand.iter().filter(|c| c.0 == suit).count() as i32)
+        .collect::<Vec<_>>();
+    let mut cards = most_valuable(hand, hand.len(), |i, card| {
+        let mut keep = card.1 as i32 + rules.counters.points(card.1) * 2;
+        if meld[i] {
+            keep += 100;
+        }
+        if card.0 == trump {
+            keep += 80;
+        } else {
+            keep += suit_lengths[card.0 as usize] * 3;
+        }
+        if card.1 == Rank::Ace {
+            keep += 50;
+        }
+        keep
+    });
+    cards.split_off(hand.len().saturating_sub(count))
+}
+
+/// The indices into `hand` of `cards`, each card in `hand` used at most once.
+pub(super) fn indices_of(hand: &[Card], cards: &[Card]) -> Vec<usize> {
+    let mut used = vec![false; hand.len()];
+    cards
+        .iter()
+        .map(|card| {
+            let index = (0..hand.len())
+                .find(|i| !used[*i] && hand[*i] == *card)
+                .expect("the card is in the hand");
+            used[index] = true;
+            index
+        })
+        .collect()
+}
+
+impl Bot {
+    /// Picks the cards to pass from `hand`, either as the bid winner's
+    /// partner passing to them, or as the bid winner passing back.
+    pub fn choose_pass(hand: &[Card], trump: Suit, to_bid_winner: bool, rules: &Rules) -> Action {
+        let count = rules.passing.count;
+        let cards = if to_bid_winner {
+            partner_pass(hand, trump, count)
+        } else {
+            bid_winner_pass(hand, trump, count, rules)
+        };
+        Action::Pass(indices_of(hand, &cards))
+    }
+}
+
+#[test]
+fn test_partner_pass() {
+    use super::parse_hand;
+
+    let hand = parse_hand("9S AH JS TS KC QC AC 9D JD TD 9H TH");
+    let mut pass = partner_pass(&hand, Suit::Spades, 4);
+    pass.sort();
+    assert_eq!(pass, parse_hand("AH 9S JS TS"));
+
+    let hand = parse_hand("9S AH KC QC AC TC 9D JD TD 9H TH AD");
+    let mut pass = partner_pass(&hand, Suit::Hearts, 4);
+    pass.sort();
+    assert_eq!(pass, parse_hand("AC 9H TH AH"));
+}
+
+#[test]
+fn test_bid_winner_pass() {
+    use super::parse_hand;
+
+    let rules = Rules::default();
+    let hand = parse_hand("AS TS KS QS JS 9S QS JD AH AC 9C JC 9H JH 9D KD");
+    let mut pass = bid_winner_pass(&hand, Suit::Spades, 4, &rules);
+    pass.sort();
+    assert_eq!(pass, parse_hand("9D KD 9C 9H"));
+
+    let mut pass = bid_winner_pass(&hand, Suit::Spades, 3, &rules);
+    pass.sort();
+    assert_eq!(pass, parse_hand("9D 9C 9H"));
+}
+
+#[test]
+fn test_choose_pass() {
+    use super::parse_hand;
+
+    let rules = Rules::default();
+    let hand = parse_hand("AS AS 9H 9H 9H 9D 9D JD TC TC KH QH");
+    let Action::Pass(indices) = Bot::choose_pass(&hand, Suit::Spades, true, &rules) else {
+        panic!("expected a pass");
+    };
+    let mut cards: Vec<_> = indices.iter().map(|i| hand[*i]).collect();
+    cards.sort();
+    assert_eq!(indices.len(), 4);
+    assert_eq!(cards, parse_hand("JD TC AS AS"));
+}
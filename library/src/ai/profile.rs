@@ -0,0 +1,135 @@
+use super::BotConfig;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// How strong a bot plays.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    /// Searches briefly, ignores what the play reveals about other hands,
+    /// and often plays a worse card on purpose.
+    Easy,
+    /// Searches a little and now and then plays a worse card.
+    Medium,
+    /// The bot as it plays without any settings.
+    #[default]
+    Hard,
+    /// Searches and samples far more, and solves more of the hand exactly.
+    Expert,
+}
+
+/// Reads a difficulty by its name in any case, like `easy` or `Expert`.
+impl FromStr for Difficulty {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            "expert" => Ok(Difficulty::Expert),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How much risk a bot takes when bidding.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BiddingStyle {
+    /// Bids well under what the hand looks to be worth.
+    Conservative,
+    #[default]
+    Balanced,
+    /// Bids close to, or a little over, what the hand looks to be worth.
+    Aggressive,
+}
+
+impl BiddingStyle {
+    /// How far below its estimate of the hand the bot bids.
+    pub(super) fn safety_margin(self) -> f32 {
+        match self {
+            BiddingStyle::Conservative => 40.0,
+            BiddingStyle::Balanced => 15.0,
+            BiddingStyle::Aggressive => -10.0,
+        }
+    }
+}
+
+/// The settings a bot in a seat is created with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BotProfile {
+    pub difficulty: Difficulty,
+    pub bidding: BiddingStyle,
+}
+
+impl BotProfile {
+    pub fn config(&self) -> BotConfig {
+        let config = BotConfig {
+            bidding: self.bidding,
+            ..Default::default()
+        };
+        match self.difficulty {
+            Difficulty::Easy => BotConfig {
+                max_iterations: Some(300),
+                solve_below: 0,
+                enumerate_below: 0,
+                bidding_samples: 20,
+                inference: false,
+                mistake_rate: 0.25,
+                ..config
+            },
+            Difficulty::Medium => BotConfig {
+                max_iterations: Some(2000),
+                solve_below: 8,
+                solver_samples: 30,
+                enumerate_below: 8,
+                bidding_samples: 60,
+                mistake_rate: 0.05,
+                ..config
+            },
+            Difficulty::Hard => config,
+            Difficulty::Expert => BotConfig {
+                max_iterations: Some(40000),
+                solve_below: 20,
+                solver_samples: 200,
+                enumerate_below: 16,
+                bidding_samples: 400,
+                ..config
+            },
+        }
+    }
+}
+
+#[test]
+fn test_bidding_styles() {
+    use super::{parse_hand, Bot};
+    use crate::rules::Rules;
+    use crate::Player::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let hand = parse_hand("AS TS KS QS JS 9S AH AC AD KH QH JD");
+    let bids = [
+        BiddingStyle::Conservative,
+        BiddingStyle::Balanced,
+        BiddingStyle::Aggressive,
+    ]
+    .map(|bidding| {
+        let config = BotProfile {
+            bidding,
+            ..Default::default()
+        }
+        .config();
+        let mut rng = StdRng::seed_from_u64(9);
+        Bot::choose_bid(A, &hand, A, &[], &Rules::default(), &config, &mut rng)
+    });
+    assert!(bids[0] < bids[1] && bids[1] < bids[2], "{bids:?}");
+
+    let easy = BotProfile {
+        difficulty: Difficulty::Easy,
+        ..Default::default()
+    }
+    .config();
+    assert!(!easy.inference);
+    assert_eq!(BotProfile::default().config(), BotConfig::default());
+}
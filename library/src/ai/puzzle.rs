@@ -0,0 +1,388 @@
+use super::{Bot, BotConfig};
+use crate::{Card, Player, PlayingPhase, Suit, Trick};
+use enum_iterator::all;
+use itertools::Itertools;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// A position from the play with the card or cards a good player would
+/// play, for checking the bot against.
+///
+/// Puzzles are written one per paragraph, with a `key: value` on each line
+/// and `#` starting a comment:
+///
+/// ```text
+/// name: feed-partner
+/// trump: S
+/// bid_winner: A
+/// player: C
+/// hands: AH AH KS QC | QS KH AC | TH JH 9C QD | QH AS JS AD
+/// known: A AH AH; D QH
+/// void: B S
+/// trick: B 9H
+/// best: TH
+/// ```
+///
+/// `hands` lists every player's hand from A to D, and every card in none of
+/// them has been played. The bot sees its own hand, the cards `known` to be
+/// in other hands, and the suits other players have shown `void` in.
+/// `trick` is the leader followed by the cards played to the trick so far,
+/// and can be left out when `player` leads. `best` lists the cards that
+/// count as a pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    pub name: String,
+    pub trump: Suit,
+    pub bid_winner: Player,
+    pub player: Player,
+    pub hands: [Vec<Card>; 4],
+    pub known: [Vec<Card>; 4],
+    pub voids: [Vec<Suit>; 4],
+    pub trick: Trick,
+    pub best: Vec<Card>,
+}
+
+/// Why a puzzle file couldn't be read, with the line it went wrong on,
+/// counting from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePuzzleError {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl Display for ParsePuzzleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+/// Reads a single `what`, such as a player or a suit.
+fn parse_one<T: FromStr>(s: &str, what: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("{s:?} isn't a {what}"))
+}
+
+/// Reads a list of `what` separated by spaces.
+fn parse_list<T: FromStr>(s: &str, what: &str) -> Result<Vec<T>, String> {
+    s.split_whitespace()
+        .map(|word| parse_one(word, what))
+        .collect()
+}
+
+/// Reads a field's value with `parse`, blaming its line if it fails.
+fn parse<T>(
+    (line, value): (usize, &str),
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<T, ParsePuzzleError> {
+    parse(value).map_err(|reason| ParsePuzzleError { line, reason })
+}
+
+/// Reads lists like `A AH AH; D QH`, of a player followed by the `what`
+/// said about them.
+fn parse_per_player<T: FromStr>(s: &str, what: &str) -> Result<[Vec<T>; 4], String> {
+    let mut lists: [Vec<T>; 4] = Default::default();
+    for entry in s.split(';') {
+        let entry = entry.trim();
+        let (player, list) = entry.split_once(' ').unwrap_or((entry, ""));
+        let player: Player = parse_one(player, "player")?;
+        lists[player as usize].extend(parse_list(list, what)?);
+    }
+    Ok(lists)
+}
+
+impl Puzzle {
+    /// Reads a puzzle from its lines, each paired with its line number.
+    fn parse(lines: &[(usize, &str)]) -> Result<Self, ParsePuzzleError> {
+        let first_line = lines.first().map_or(0, |(number, _)| *number);
+        let mut fields = vec![];
+        for (number, line) in lines {
+            let error = |reason: String| ParsePuzzleError {
+                line: *number,
+                reason,
+            };
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| error(format!("expected `key: value`, got {line:?}")))?;
+            fields.push((*number, key.trim(), value.trim()));
+        }
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|(_, k, _)| *k == key)
+                .map(|(number, _, value)| (*number, *value))
+        };
+        let required = |key: &str| {
+            field(key).ok_or_else(|| ParsePuzzleError {
+                line: first_line,
+                reason: format!("missing `{key}`"),
+            })
+        };
+
+        let name = required("name")?.1.to_string();
+        let trump = parse(required("trump")?, |s| parse_one(s, "suit"))?;
+        let bid_winner = parse(required("bid_winner")?, |s| parse_one(s, "player"))?;
+        let player = parse(required("player")?, |s| parse_one(s, "player"))?;
+        let hands = parse(required("hands")?, |s| {
+            let hands: Vec<_> = s
+                .split('|')
+                .map(|hand| parse_list(hand, "card"))
+                .collect::<Result<_, _>>()?;
+            hands
+                .try_into()
+                .map_err(|_| "expected four hands".to_string())
+        })?;
+        let known = match field("known") {
+            Some(field) => parse(field, |s| parse_per_player(s, "card"))?,
+            None => Default::default(),
+        };
+        let voids = match field("void") {
+            Some(field) => parse(field, |s| parse_per_player(s, "suit"))?,
+            None => Default::default(),
+        };
+        let trick = match field("trick") {
+            Some(field) => parse(field, |s| {
+                let (leader, cards) = s.split_once(' ').unwrap_or((s, ""));
+                Ok(Trick {
+                    first_player: parse_one(leader, "player")?,
+                    cards: parse_list(cards, "card")?,
+                })
+            })?,
+            None => Trick {
+                first_player: player,
+                cards: vec![],
+            },
+        };
+        let best = parse(required("best")?, |s| parse_list(s, "card"))?;
+
+        let puzzle = Self {
+            name,
+            trump,
+            bid_winner,
+            player,
+            hands,
+            known,
+            voids,
+            trick,
+            best,
+        };
+        puzzle.check().map_err(|reason| ParsePuzzleError {
+            line: first_line,
+            reason,
+        })?;
+        Ok(puzzle)
+    }
+
+    /// Makes sure the puzzle could come up in a game.
+    fn check(&self) -> Result<(), String> {
+        for card in all::<Card>() {
+            let copies = self
+                .hands
+                .iter()
+                .flatten()
+                .chain(&self.trick.cards)
+                .filter(|c| **c == card)
+                .count();
+            if copies > 2 {
+                return Err(format!("{card} is dealt {copies} times"));
+            }
+        }
+        let to_play = crate::each_player(self.trick.first_player).nth(self.trick.cards.len());
+        if to_play != Some(self.player) {
+            return Err(format!("it isn't {:?}'s turn", self.player));
+        }
+        // Whoever has played to the trick holds one card fewer.
+        let size = self.hands[self.player as usize].len();
+        if size == 0 {
+            return Err(format!("{:?} has no card to play", self.player));
+        }
+        for (played, player) in crate::each_player(self.trick.first_player).enumerate() {
+            let expected = size - usize::from(played < self.trick.cards.len());
+            let held = self.hands[player as usize].len();
+            if held != expected {
+                return Err(format!(
+                    "{player:?}'s hand doesn't fit the trick: expected {expected} cards, got {held}"
+                ));
+            }
+        }
+        for (player, known) in self.known.iter().enumerate() {
+            let hand = &self.hands[player];
+            if let Some(card) = known.iter().find(|card| {
+                known.iter().filter(|c| c == card).count()
+                    > hand.iter().filter(|c| c == card).count()
+            }) {
+                return Err(format!("{card} is known to be in a hand without it"));
+            }
+        }
+        match self
+            .best
+            .iter()
+            .find(|card| !self.hands[self.player as usize].contains(card))
+        {
+            Some(card) => Err(format!("{card} isn't in {:?}'s hand", self.player)),
+            None => Ok(()),
+        }
+    }
+
+    /// A bot in `player`'s seat that knows what the puzzle lets it see.
+    pub fn bot(&self, config: BotConfig) -> Bot {
+        let phase = PlayingPhase {
+            trump: self.trump,
+            bid_winner: self.bid_winner,
+            highest_bid: 0,
+            extra_points: Default::default(),
+            counters: Default::default(),
+            set_penalty: Default::default(),
+            piles: Default::default(),
+            trick: self.trick.clone(),
+        };
+        let hand = self.hands[self.player as usize].clone();
+        let mut bot = Bot::with_config(self.player, hand, phase, config);
+
+        let state = &mut bot.state;
+        state.deal(&self.hands);
+        for player in all::<Player>().filter(|player| *player != self.player) {
+            state.add_known_cards(player, &self.known[player as usize]);
+            for suit in &self.voids[player as usize] {
+                state
+                    .player_state_mut(player)
+                    .set_highest_possible(*suit, None);
+            }
+        }
+        bot
+    }
+}
+
+/// Writes the puzzle in the form `parse_puzzles` reads, with the suits as
+/// symbols.
+impl Display for Puzzle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cards = |cards: &[Card]| cards.iter().join(" ");
+        let per_player = |lists: Vec<String>| {
+            all::<Player>()
+                .zip(lists)
+                .filter(|(_, list)| !list.is_empty())
+                .map(|(player, list)| format!("{player:?} {list}"))
+                .join("; ")
+        };
+
+        writeln!(f, "name: {}", self.name)?;
+        writeln!(f, "trump: {}", self.trump)?;
+        writeln!(f, "bid_winner: {:?}", self.bid_winner)?;
+        writeln!(f, "player: {:?}", self.player)?;
+        writeln!(
+            f,
+            "hands: {}",
+            self.hands.iter().map(|hand| cards(hand)).join(" | ")
+        )?;
+        let known = per_player(self.known.iter().map(|known| cards(known)).collect());
+        if !known.is_empty() {
+            writeln!(f, "known: {known}")?;
+        }
+        let voids = per_player(
+            self.voids
+                .iter()
+                .map(|voids| voids.iter().join(" "))
+                .collect(),
+        );
+        if !voids.is_empty() {
+            writeln!(f, "void: {voids}")?;
+        }
+        if !self.trick.cards.is_empty() {
+            writeln!(
+                f,
+                "trick: {:?} {}",
+                self.trick.first_player,
+                cards(&self.trick.cards)
+            )?;
+        }
+        writeln!(f, "best: {}", cards(&self.best))
+    }
+}
+
+/// Reads every puzzle in `text`, which are separated by blank lines.
+pub fn parse_puzzles(text: &str) -> Result<Vec<Puzzle>, ParsePuzzleError> {
+    let lines: Vec<_> = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split('#').next().unwrap().trim()))
+        .collect();
+    lines
+        .split(|(_, line)| line.is_empty())
+        .filter(|lines| !lines.is_empty())
+        .map(Puzzle::parse)
+        .collect()
+}
+
+/// What the bot played in one puzzle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PuzzleResult {
+    pub name: String,
+    pub played: Card,
+    pub passed: bool,
+}
+
+/// How the bot did on a set of puzzles.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PuzzleReport {
+    pub results: Vec<PuzzleResult>,
+}
+
+impl PuzzleReport {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|result| result.passed).count()
+    }
+
+    /// The share of puzzles passed, from 0 to 1.
+    pub fn pass_rate(&self) -> f64 {
+        self.passed() as f64 / self.results.len().max(1) as f64
+    }
+}
+
+impl Display for PuzzleReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for result in &self.results {
+            let verdict = if result.passed { "pass" } else { "FAIL" };
+            writeln!(f, "{verdict} {} (played {})", result.name, result.played)?;
+        }
+        write!(
+            f,
+            "{}/{} passed ({:.0}%)",
+            self.passed(),
+            self.results.len(),
+            self.pass_rate() * 100.0
+        )
+    }
+}
+
+/// Has a bot set up with `config` play each puzzle. Give `config` a seed and
+/// an iteration limit to get the same report on every run.
+pub fn run_puzzles(puzzles: &[Puzzle], config: &BotConfig) -> PuzzleReport {
+    PuzzleReport {
+        results: puzzles
+            .iter()
+            .map(|puzzle| {
+                let played = puzzle.bot(*config).get_move();
+                PuzzleResult {
+                    name: puzzle.name.clone(),
+                    played,
+                    passed: puzzle.best.contains(&played),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Reads the puzzles that ship with the library. How the bot does on them is
+/// reported by `pinochle-cli puzzles`.
+#[test]
+fn test_puzzles() {
+    let puzzles = parse_puzzles(include_str!("../../puzzles.txt")).unwrap();
+    let written: String = puzzles.iter().map(|puzzle| format!("{puzzle}\n")).collect();
+    assert_eq!(parse_puzzles(&written).unwrap(), puzzles);
+    let error = parse_puzzles("name: broken\ntrump: X").unwrap_err();
+    assert_eq!(error.line, 2);
+    let uneven = "name: uneven\ntrump: S\nbid_winner: A\nplayer: A\n\
+        hands: AS 9H | TH | 9C JD | AH QC\nbest: AS";
+    let error = parse_puzzles(uneven).unwrap_err();
+    assert_eq!(error.line, 1);
+    assert!(error.reason.starts_with("B's hand"));
+}
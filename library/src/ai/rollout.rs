@@ -0,0 +1,230 @@
+use crate::{
+    compare, each_player, is_legal_play, partner, trick_winner, Card, Player, PlayingPhase, Rank,
+};
+use enum_iterator::all;
+use rand::prelude::SliceRandom;
+use rand::RngCore;
+use std::fmt::Debug;
+
+/// Picks the cards played in a simulated deal once the search has nothing
+/// better to go on.
+pub trait RolloutPolicy: Debug + Send + Sync {
+    /// Picks one of `legal`, the distinct cards `player` may play next.
+    /// `legal` is never empty.
+    fn choose(
+        &self,
+        phase: &PlayingPhase,
+        player: Player,
+        legal: &[Card],
+        rng: &mut dyn RngCore,
+    ) -> Card;
+
+    /// Like `choose`, with `partner_hand`, what `player`'s partner holds in
+    /// the deal being played out, in view. The simulated deals are played
+    /// out with this. By default the partner's hand is ignored.
+    fn choose_with_partner(
+        &self,
+        phase: &PlayingPhase,
+        player: Player,
+        partner_hand: &[Card],
+        legal: &[Card],
+        rng: &mut dyn RngCore,
+    ) -> Card {
+        let _ = partner_hand;
+        self.choose(phase, player, legal, rng)
+    }
+}
+
+/// Plays any legal card.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RandomRollout;
+
+impl RolloutPolicy for RandomRollout {
+    fn choose(
+        &self,
+        _phase: &PlayingPhase,
+        _player: Player,
+        legal: &[Card],
+        rng: &mut dyn RngCore,
+    ) -> Card {
+        *legal.choose(rng).unwrap()
+    }
+}
+
+/// Plays roughly the way a sensible player would: wins a trick with the
+/// lowest card that takes it, throws counters onto a trick its partner is
+/// winning or is sure to win playing last, and throws non-counters onto one
+/// it has lost. When leading it cashes an ace in a side suit, then leads low
+/// into a side suit its partner holds the ace of, or otherwise leads low.
+///
+/// In the simulated deals each player sees its partner's hand, which a real
+/// partnership can only guess at. That makes both teams' play look better
+/// coordinated than it is, so the deals tend towards what open partnerships
+/// would score.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HeuristicRollout;
+
+impl RolloutPolicy for HeuristicRollout {
+    fn choose(
+        &self,
+        phase: &PlayingPhase,
+        player: Player,
+        legal: &[Card],
+        rng: &mut dyn RngCore,
+    ) -> Card {
+        self.choose_with_partner(phase, player, &[], legal, rng)
+    }
+
+    fn choose_with_partner(
+        &self,
+        phase: &PlayingPhase,
+        player: Player,
+        partner_hand: &[Card],
+        legal: &[Card],
+        rng: &mut dyn RngCore,
+    ) -> Card {
+        let points = |card: &Card| phase.counters.points(card.1);
+        let trick = &phase.trick.cards;
+        let Some(lead) = trick.first() else {
+            let side_ace = |card: &Card| card.0 != phase.trump && card.1 == Rank::Ace;
+            let aces: Vec<_> = legal.iter().copied().filter(side_ace).collect();
+            if let Some(ace) = aces.choose(rng) {
+                return *ace;
+            }
+            let into_partner: Vec<_> = legal
+                .iter()
+                .copied()
+                .filter(|card| partner_hand.iter().any(|c| side_ace(c) && c.0 == card.0))
+                .collect();
+            if !into_partner.is_empty() {
+                return lowest(&into_partner, |card| (points(card), card.1), rng);
+            }
+            return lowest(legal, |card| (card.0 == phase.trump, card.1), rng);
+        };
+
+        let winner = trick_winner(trick, phase.trump);
+        let winning_card = trick[winner];
+        if each_player(phase.trick.first_player).nth(winner) == Some(partner(player))
+            || partner_takes_it(phase, partner_hand, legal)
+        {
+            return lowest(legal, |card| (-points(card), card.1), rng);
+        }
+
+        let winning: Vec<_> = legal
+            .iter()
+            .copied()
+            .filter(|card| compare(*card, winning_card, phase.trump, lead.0).is_gt())
+            .collect();
+        if !winning.is_empty() {
+            return lowest(&winning, |card| (card.0 == phase.trump, card.1), rng);
+        }
+        lowest(legal, |card| (points(card), card.1), rng)
+    }
+}
+
+/// Whether the partner of the second player to a trick plays last and holds
+/// a card nobody left to play can beat, going by the cards out of play and
+/// in view.
+fn partner_takes_it(phase: &PlayingPhase, partner_hand: &[Card], legal: &[Card]) -> bool {
+    let trick = &phase.trick.cards;
+    let [lead] = trick[..] else {
+        return false;
+    };
+    let beats = |a: Card, b: Card| compare(a, b, phase.trump, lead.0).is_gt();
+    let Some(best) = partner_hand
+        .iter()
+        .copied()
+        .filter(|card| is_legal_play(trick, partner_hand, *card, phase.trump))
+        .reduce(|best, card| if beats(card, best) { card } else { best })
+    else {
+        return false;
+    };
+    if !beats(best, lead) {
+        return false;
+    }
+    let seen: Vec<_> = phase
+        .piles
+        .iter()
+        .flatten()
+        .chain(partner_hand)
+        .chain(legal)
+        .collect();
+    // The third player takes it with a higher card, or an equal one since
+    // it's played first.
+    all::<Card>()
+        .filter(|card| !beats(best, *card))
+        .all(|card| seen.iter().filter(|seen| ***seen == card).count() == 2)
+}
+
+/// One of the cards with the least `key`, picked at random.
+fn lowest<K, F>(cards: &[Card], key: F, rng: &mut dyn RngCore) -> Card
+where
+    K: Ord,
+    F: Fn(&Card) -> K,
+{
+    let least = cards.iter().map(&key).min().unwrap();
+    let lowest: Vec<_> = cards.iter().filter(|card| key(card) == least).collect();
+    **lowest.choose(rng).unwrap()
+}
+
+#[test]
+fn test_heuristic_rollout() {
+    use super::{parse_hand, test_phase};
+    use crate::Suit;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut phase = test_phase(Suit::Spades, Player::A, "QH JH");
+    let legal = parse_hand("AH TH KH");
+    let mut choose =
+        |phase: &PlayingPhase| HeuristicRollout.choose(phase, Player::C, &legal, &mut rng);
+    // Partner A is winning, so C throws the ten rather than the ace.
+    assert_eq!(choose(&phase), parse_hand("TH")[0]);
+    // B is winning, so C takes it as cheaply as it can.
+    phase.trick.cards = parse_hand("JH QH");
+    assert_eq!(choose(&phase), parse_hand("KH")[0]);
+    // B has trumped it, so C throws the card worth least.
+    phase.trick.cards = parse_hand("JH 9S");
+    assert_eq!(choose(&phase), parse_hand("KH")[0]);
+}
+
+#[test]
+fn test_rollout_plays_to_partner() {
+    use super::{parse_hand, test_phase};
+    use crate::Suit;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(2);
+    let phase = PlayingPhase {
+        piles: [parse_hand("AS AS"), vec![]],
+        ..test_phase(Suit::Spades, Player::B, "9S")
+    };
+    // A plays last, and with both aces gone nobody can beat its tens, so C
+    // feeds it the king.
+    let legal = parse_hand("KS JS");
+    let mut choose = |partner_hand: &str| {
+        HeuristicRollout.choose_with_partner(
+            &phase,
+            Player::C,
+            &parse_hand(partner_hand),
+            &legal,
+            &mut rng,
+        )
+    };
+    assert_eq!(choose("TS TS"), parse_hand("KS")[0]);
+    // With the other ten still out, D might take it, so C wins it cheaply.
+    assert_eq!(choose("TS 9C"), parse_hand("JS")[0]);
+    // With no ace to cash, A leads low into the suit C holds the ace of.
+    let phase = test_phase(Suit::Spades, Player::A, "");
+    let legal = parse_hand("KH 9H JC 9D");
+    let card = HeuristicRollout.choose_with_partner(
+        &phase,
+        Player::A,
+        &parse_hand("AH QD"),
+        &legal,
+        &mut rng,
+    );
+    assert_eq!(card, parse_hand("9H")[0]);
+}
@@ -0,0 +1,563 @@
+use super::{merge_stats, Bot, CardAnalysis, Deal, Inconsistency, ScoreStats, State};
+use crate::rules::CounterScheme;
+use crate::{compare, each_player, is_legal_play, trick_winner, Card, Player, RoundSummary, Suit};
+use enum_iterator::next_cycle;
+use itertools::Itertools;
+use ordered_float::NotNan;
+use rand::Rng;
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Larger than any number of points a hand can be worth.
+const INFINITY: i32 = 1_000_000;
+/// The most layouts of the unseen cards the bot solves one by one before
+/// falling back to sampling them.
+const MAX_LAYOUTS: usize = 5000;
+
+/// What a searched position is known to be worth. Alpha-beta cutoffs leave
+/// some positions with only one side of their value pinned down.
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    lower: i32,
+    upper: i32,
+}
+
+/// A position during the play with every hand in view.
+struct Position {
+    hands: [Vec<Card>; 4],
+    trick: Vec<Card>,
+    leader: Player,
+    current_player: Player,
+}
+
+/// The index of a card among the 24 distinct cards.
+fn card_index(card: Card) -> usize {
+    card.0 as usize * 6 + card.1 as usize
+}
+
+/// Packs a hand into two bits per distinct card, holding how many copies of
+/// the card are in the hand.
+fn pack(hand: &[Card]) -> u64 {
+    hand.iter().map(|card| 1 << (2 * card_index(*card))).sum()
+}
+
+/// An alpha-beta search over the rest of a hand with every card in view,
+/// finding the trick points `team` takes when everyone plays perfectly.
+pub(super) struct Solver {
+    trump: Suit,
+    counters: CounterScheme,
+    team: usize,
+    /// Positions at the start of a trick, keyed by what's left in each hand
+    /// and who leads. What was taken before doesn't change what the rest of
+    /// the hand is worth, so the same entry serves every way of getting
+    /// there.
+    table: HashMap<([u64; 4], u8), Bounds>,
+}
+
+impl Solver {
+    pub(super) fn new(trump: Suit, counters: CounterScheme, team: usize) -> Self {
+        Self {
+            trump,
+            counters,
+            team,
+            table: HashMap::new(),
+        }
+    }
+
+    /// The trick points `team` takes from here to the end of the hand,
+    /// including the cards already in the current trick.
+    pub(super) fn solve(&mut self, deal: &Deal) -> i32 {
+        let mut position = Position {
+            hands: deal.hands.clone(),
+            trick: deal.phase.trick.cards.clone(),
+            leader: deal.phase.trick.first_player,
+            current_player: deal.current_player,
+        };
+        self.search(&mut position, -INFINITY, INFINITY)
+    }
+
+    fn search(&mut self, position: &mut Position, mut alpha: i32, mut beta: i32) -> i32 {
+        let (alpha_start, beta_start) = (alpha, beta);
+        let key = if position.trick.is_empty() {
+            if position.hands.iter().all(Vec::is_empty) {
+                return 0;
+            }
+            let key = (
+                position.hands.each_ref().map(|hand| pack(hand)),
+                position.leader as u8,
+            );
+            if let Some(bounds) = self.table.get(&key) {
+                if bounds.lower >= beta || bounds.lower == bounds.upper {
+                    return bounds.lower;
+                }
+                if bounds.upper <= alpha {
+                    return bounds.upper;
+                }
+                alpha = alpha.max(bounds.lower);
+                beta = beta.min(bounds.upper);
+            }
+            Some(key)
+        } else {
+            None
+        };
+
+        let maximizing = position.current_player as usize % 2 == self.team;
+        let mut best = if maximizing { -INFINITY } else { INFINITY };
+        for card in self.ordered_moves(position) {
+            let value = self.play(position, card, alpha, beta);
+            if maximizing {
+                best = best.max(value);
+                alpha = alpha.max(value);
+            } else {
+                best = best.min(value);
+                beta = beta.min(value);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if let Some(key) = key {
+            let known = self.table.entry(key).or_insert(Bounds {
+                lower: -INFINITY,
+                upper: INFINITY,
+            });
+            if best > alpha_start {
+                known.lower = known.lower.max(best);
+            }
+            if best < beta_start {
+                known.upper = known.upper.min(best);
+            }
+        }
+        best
+    }
+
+    /// Plays `card` for the current player, searches the position that
+    /// leaves, and takes the card back.
+    fn play(&mut self, position: &mut Position, card: Card, alpha: i32, beta: i32) -> i32 {
+        let player = position.current_player;
+        let hand = &mut position.hands[player as usize];
+        hand.remove(hand.iter().position(|c| *c == card).unwrap());
+        position.trick.push(card);
+
+        let value = if position.trick.len() == 4 {
+            let winner = each_player(position.leader)
+                .nth(trick_winner(&position.trick, self.trump))
+                .unwrap();
+            let mut points = position
+                .trick
+                .iter()
+                .map(|card| self.counters.points(card.1))
+                .sum::<i32>();
+            if position.hands.iter().all(Vec::is_empty) {
+                points += self.counters.last_trick();
+            }
+            let gained = if winner as usize % 2 == self.team {
+                points
+            } else {
+                0
+            };
+
+            let trick = std::mem::take(&mut position.trick);
+            let leader = position.leader;
+            position.leader = winner;
+            position.current_player = winner;
+            let value = gained + self.search(position, alpha - gained, beta - gained);
+            position.trick = trick;
+            position.leader = leader;
+            value
+        } else {
+            position.current_player = next_cycle(&player).unwrap();
+            self.search(position, alpha, beta)
+        };
+
+        position.current_player = player;
+        position.trick.pop();
+        position.hands[player as usize].push(card);
+        value
+    }
+
+    /// The current player's distinct legal cards, with those likely to be
+    /// best tried first so more of the search gets cut off: the highest when
+    /// leading, and otherwise cards that take the trick before those that
+    /// don't.
+    fn ordered_moves(&self, position: &Position) -> Vec<Card> {
+        let hand = &position.hands[position.current_player as usize];
+        let mut moves: Vec<_> = hand
+            .iter()
+            .copied()
+            .filter(|card| is_legal_play(&position.trick, hand, *card, self.trump))
+            .unique()
+            .collect();
+        match position.trick.first() {
+            None => moves.sort_by_key(|card| Reverse((card.0 == self.trump, card.1))),
+            Some(lead) => {
+                let best = position
+                    .trick
+                    .iter()
+                    .copied()
+                    .reduce(|best, card| match compare(card, best, self.trump, lead.0) {
+                        Ordering::Greater => card,
+                        _ => best,
+                    })
+                    .unwrap();
+                moves.sort_by_key(|card| {
+                    let wins = compare(*card, best, self.trump, lead.0) == Ordering::Greater;
+                    Reverse((wins, card.1))
+                })
+            }
+        }
+        moves
+    }
+}
+
+impl Bot {
+    /// Analyzes each card by perfect information Monte Carlo: each sampled
+    /// deal is solved exactly after each legal card. The cards are ordered by
+    /// how well they meet the bot's objective on average, best first, then
+    /// by their average score. The time limit is counted from `start`.
+    pub(super) fn solve_sampled<R>(&self, start: Instant, rng: &mut R) -> Vec<CardAnalysis>
+    where
+        R: Rng + ?Sized,
+    {
+        let team = self.player as usize % 2;
+        let Ok(sampler) = self.state.sampler(Some(self.config.solver_samples), rng) else {
+            return vec![];
+        };
+        let workers = self.run_workers(
+            start,
+            Some(self.config.solver_samples),
+            rng,
+            |_, rng, budget| {
+                let mut scores: HashMap<Card, ScoreStats> = HashMap::new();
+                let mut samples = 0;
+                while !budget.spent(samples) {
+                    samples += 1;
+                    let Ok(hands) = sampler.sample(rng) else {
+                        continue;
+                    };
+                    let deal = Deal {
+                        phase: self.playing_phase.clone(),
+                        current_player: self.player,
+                        hands,
+                    };
+                    let mut solver = Solver::new(deal.phase.trump, deal.phase.counters, team);
+                    for card in deal.legal_moves() {
+                        let mut next = deal.clone();
+                        let summary = match next.play(card) {
+                            Some(summary) => summary,
+                            None => next.solved_summary(&mut solver),
+                        };
+                        let utility = self.config.utility(&summary, team);
+                        scores.entry(card).or_default().add(&summary, team, utility);
+                    }
+                }
+                scores
+            },
+        );
+        ranked(merge_stats(workers))
+    }
+
+    /// Solves the hand exactly from every layout of the unseen cards after
+    /// each legal card, with each layout counted by how likely it is, so the
+    /// cards come out ordered by their true averages rather than a sample's.
+    /// Gives `None` if there are too many layouts to go through, or if the
+    /// time limit, counted from `start`, runs out before they're all solved.
+    pub(super) fn solve_exhaustive<R>(
+        &self,
+        start: Instant,
+        rng: &mut R,
+    ) -> Result<Option<Vec<CardAnalysis>>, Inconsistency>
+    where
+        R: Rng + ?Sized,
+    {
+        let Some(layouts) = self.state.layouts(MAX_LAYOUTS)? else {
+            return Ok(None);
+        };
+        let team = self.player as usize % 2;
+        let workers = self.run_workers(start, Some(layouts.len()), rng, |first, _, budget| {
+            let mut scores: HashMap<Card, ScoreStats> = HashMap::new();
+            let phase = &self.playing_phase;
+            let mut solver = Solver::new(phase.trump, phase.counters, team);
+            for (hands, weight) in &layouts[first..first + budget.max_iterations] {
+                if budget.timed_out() {
+                    return None;
+                }
+                let deal = Deal {
+                    phase: phase.clone(),
+                    current_player: self.player,
+                    hands: hands.clone(),
+                };
+                for card in deal.legal_moves() {
+                    let mut next = deal.clone();
+                    let summary = match next.play(card) {
+                        Some(summary) => summary,
+                        None => next.solved_summary(&mut solver),
+                    };
+                    let utility = self.config.utility(&summary, team);
+                    scores
+                        .entry(card)
+                        .or_default()
+                        .add_weighted(&summary, team, utility, *weight);
+                }
+            }
+            Some(scores)
+        });
+        Ok(workers
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .map(|workers| ranked(merge_stats(workers))))
+    }
+}
+
+/// The solved cards ordered by how well they meet the bot's objective on
+/// average, best first, then by their average score.
+fn ranked(scores: HashMap<Card, ScoreStats>) -> Vec<CardAnalysis> {
+    let mut analysis: Vec<_> = scores
+        .into_iter()
+        .map(|(card, scores)| scores.analysis(card))
+        .collect();
+    analysis.sort_by_key(|card| {
+        (
+            Reverse(NotNan::new(card.utility).unwrap()),
+            Reverse(NotNan::new(card.expected_score).unwrap()),
+            card.card,
+        )
+    });
+    analysis
+}
+
+/// A way the unseen cards could lie, with how likely it is up to a constant.
+type Layout = ([Vec<Card>; 4], f64);
+
+impl State {
+    /// Every way the cards the bot hasn't seen could be split among the
+    /// hands, or `None` if there are more than `limit`. The two copies of a
+    /// card are alike, so a layout that splits them between two hands stands
+    /// for twice as many deals as one that gives both to the same hand.
+    fn layouts(&self, limit: usize) -> Result<Option<Vec<Layout>>, Inconsistency> {
+        let (mut hands, unknown_cards) = self.known_hands()?;
+        let mut open =
+            [0, 1, 2, 3].map(|i| (self.hand_sizes[i] as usize).saturating_sub(hands[i].len()));
+        let copies: Vec<_> = unknown_cards.into_iter().dedup_with_count().collect();
+        let mut layouts = vec![];
+        let complete = self.place(&copies, &mut hands, &mut open, 1.0, &mut layouts, limit);
+        Ok(complete.then_some(layouts))
+    }
+
+    /// Hands out each of `copies` to the players with room for it who could
+    /// hold it, adding every complete layout to `layouts`. Returns false once
+    /// there would be more than `limit` of them.
+    fn place(
+        &self,
+        copies: &[(usize, Card)],
+        hands: &mut [Vec<Card>; 4],
+        open: &mut [usize; 4],
+        weight: f64,
+        layouts: &mut Vec<Layout>,
+        limit: usize,
+    ) -> bool {
+        let Some(((count, card), rest)) = copies.split_first() else {
+            if open.iter().all(|open| *open == 0) {
+                if layouts.len() == limit {
+                    return false;
+                }
+                layouts.push((hands.clone(), weight * self.likelihood(hands)));
+            }
+            return true;
+        };
+        let factorial = |n: usize| (1..=n).product::<usize>();
+        let holders: Vec<_> = (0..4)
+            .filter(|i| open[*i] > 0 && self.players[*i].could_have_card(*card))
+            .collect();
+        for chosen in holders.into_iter().combinations_with_replacement(*count) {
+            let mut given = [0; 4];
+            for holder in chosen {
+                given[holder] += 1;
+            }
+            if (0..4).any(|i| given[i] > open[i]) {
+                continue;
+            }
+            let ways = factorial(*count) / given.iter().map(|n| factorial(*n)).product::<usize>();
+            for i in 0..4 {
+                open[i] -= given[i];
+                hands[i].extend(std::iter::repeat_n(*card, given[i]));
+            }
+            let complete = self.place(rest, hands, open, weight * ways as f64, layouts, limit);
+            for i in 0..4 {
+                open[i] += given[i];
+                hands[i].truncate(hands[i].len() - given[i]);
+            }
+            if !complete {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Deal {
+    /// How the hand ends if the rest of it is played perfectly for the
+    /// solver's team.
+    fn solved_summary(&self, solver: &mut Solver) -> RoundSummary {
+        let counters = self.phase.counters;
+        let taken = self
+            .phase
+            .piles
+            .each_ref()
+            .map(|pile| pile.iter().map(|card| counters.points(card.1)).sum::<i32>());
+        let remaining = self
+            .hands
+            .iter()
+            .flatten()
+            .chain(&self.phase.trick.cards)
+            .map(|card| counters.points(card.1))
+            .sum::<i32>()
+            + counters.last_trick();
+        let ours = solver.solve(self);
+        let mut trick_points = taken;
+        trick_points[solver.team] += ours;
+        trick_points[1 - solver.team] += remaining - ours;
+        self.phase.summarize_trick_points(trick_points)
+    }
+}
+
+#[cfg(test)]
+fn small_deal(hands: [&str; 4], trump: Suit) -> Deal {
+    use super::{parse_hand, test_phase};
+
+    Deal {
+        phase: test_phase(trump, Player::A, ""),
+        current_player: Player::A,
+        hands: hands.map(parse_hand),
+    }
+}
+
+#[test]
+fn test_solve() {
+    let deal = small_deal(["AS 9H", "TH KC", "9C JD", "AH QC"], Suit::Spades);
+    let mut solver = Solver::new(Suit::Spades, CounterScheme::Classic, 0);
+    assert_eq!(solver.solve(&deal), 30);
+
+    let mut after_ace = deal.clone();
+    after_ace.play(Card(Suit::Spades, crate::Rank::Ace));
+    assert_eq!(
+        Solver::new(Suit::Spades, CounterScheme::Classic, 0).solve(&after_ace),
+        20
+    );
+    assert_eq!(
+        Solver::new(Suit::Spades, CounterScheme::Classic, 1).solve(&deal),
+        20
+    );
+}
+
+#[test]
+fn test_solver_matches_minimax() {
+    use enum_iterator::all;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    fn minimax(deal: &Deal, team: usize) -> i32 {
+        let values = deal.legal_moves().into_iter().map(|card| {
+            let mut next = deal.clone();
+            match next.play(card) {
+                Some(summary) => summary.trick_points[team],
+                None => minimax(&next, team),
+            }
+        });
+        if deal.current_player as usize % 2 == team {
+            values.max().unwrap()
+        } else {
+            values.min().unwrap()
+        }
+    }
+
+    let mut rng = StdRng::seed_from_u64(6);
+    for _ in 0..30 {
+        let mut deck: Vec<Card> = all::<Card>().flat_map(|c| [c, c]).collect();
+        deck.shuffle(&mut rng);
+        let mut deal = small_deal(["AS", "AS", "AS", "AS"], Suit::Hearts);
+        deal.hands = [0, 1, 2, 3].map(|i| deck[i * 4..i * 4 + 4].to_vec());
+        for team in 0..2 {
+            let mut solver = Solver::new(Suit::Hearts, CounterScheme::Classic, team);
+            assert_eq!(solver.solve(&deal), minimax(&deal, team));
+        }
+    }
+}
+
+#[test]
+fn test_solve_sampled() {
+    use crate::Rank::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // With every card in view, leading the ace of trump first lets the
+    // opponents take the hearts, so the bot should lead the nine of hearts.
+    let deal = small_deal(["AS 9H", "TH KC", "9C JD", "AH QC"], Suit::Spades);
+    let mut bot = Bot::new(Player::A, deal.hands[0].clone(), deal.phase.clone());
+    bot.state.deal(&deal.hands);
+    for (player, hand) in deal.hands.iter().enumerate() {
+        bot.state.players[player].known_cards = hand.clone();
+    }
+    let analysis = bot.solve_sampled(Instant::now(), &mut StdRng::seed_from_u64(8));
+    assert_eq!(analysis[0].card, Card(Suit::Hearts, Nine));
+    assert_eq!(analysis[0].expected_score, 30.0);
+    assert_eq!(analysis[0].variance, 0.0);
+    assert_eq!(analysis[1].expected_score, 20.0);
+}
+
+#[test]
+fn test_solve_exhaustive() {
+    use super::{parse_hand, BotConfig};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::time::Duration;
+
+    // Every way the six unseen cards could be dealt, solved one deal at a
+    // time, should average out to what the enumeration finds.
+    let hand = parse_hand("AS 9H");
+    let unseen = parse_hand("TH TH KC 9C JD QC");
+    let mut deal = small_deal(["AS 9H", "TH TH", "KC 9C", "JD QC"], Suit::Spades);
+    let config = BotConfig {
+        inference: false,
+        threads: 2,
+        ..Default::default()
+    };
+    let mut bot = Bot::with_config(Player::A, hand, deal.phase.clone(), config);
+    bot.state.deal(&deal.hands);
+    deal.hands[1..].iter_mut().for_each(Vec::clear);
+
+    let mut expected: HashMap<Card, f64> = HashMap::new();
+    let mut solver = Solver::new(Suit::Spades, CounterScheme::Classic, 0);
+    let deals = (0..6).permutations(6).count() as f64;
+    for order in (0..6).permutations(6) {
+        let mut deal = deal.clone();
+        for (i, index) in order.into_iter().enumerate() {
+            deal.hands[1 + i / 2].push(unseen[index]);
+        }
+        for card in deal.legal_moves() {
+            let mut next = deal.clone();
+            next.play(card);
+            let score = next.solved_summary(&mut solver).scores[0] as f64;
+            *expected.entry(card).or_default() += score / deals;
+        }
+    }
+
+    let analysis = bot
+        .solve_exhaustive(Instant::now(), &mut StdRng::seed_from_u64(1))
+        .unwrap()
+        .unwrap();
+    // The pair of tens makes many of the 90 layouts look the same.
+    assert_eq!(analysis[0].samples, 54);
+    assert_eq!(analysis.len(), expected.len());
+    for card in &analysis {
+        assert!((card.expected_score - expected[&card.card]).abs() < 1e-9);
+    }
+
+    // Out of time, it leaves the move to the sampled solver.
+    bot.config.time_limit = Some(Duration::ZERO);
+    let analysis = bot.solve_exhaustive(Instant::now(), &mut StdRng::seed_from_u64(1));
+    assert!(analysis.unwrap().is_none());
+}
@@ -0,0 +1,275 @@
+use super::{Bot, BotConfig, PlayerView};
+use crate::rules::Rules;
+use crate::{Game, Player};
+use enum_iterator::all;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fmt::Display;
+use std::sync::atomic::{self, AtomicUsize};
+
+/// The z-score of a 95% confidence interval.
+const Z_95: f64 = 1.96;
+/// How close to 0 or 1 a win rate is taken to be when turning it into Elo,
+/// so that a clean sweep comes out large rather than infinite.
+const WIN_RATE_CLAMP: f64 = 0.001;
+
+/// Plays two bot configurations against each other as partnerships, over
+/// many seeded deals.
+///
+/// Each deal is a board of a single hand, not a game, played twice: once
+/// with the first configuration in seats A and C, and once with the same
+/// cards and the partnerships swapped, so neither side gets the better
+/// cards. The first bidder moves one seat on with each board, as it would
+/// from hand to hand in a game. Boards are played in parallel, so the bots
+/// themselves are best given a single thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tournament {
+    pub boards: usize,
+    /// Seeds the first board's deal; later boards count up from it.
+    pub seed: u64,
+    /// How many boards are played at once.
+    pub threads: usize,
+    pub rules: Rules,
+}
+
+impl Default for Tournament {
+    fn default() -> Self {
+        Self {
+            boards: 1000,
+            seed: 0,
+            threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            rules: Default::default(),
+        }
+    }
+}
+
+/// How one board went, with each configuration's score for the hand in
+/// each seating. The first seating has the first configuration in A and C.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardResult {
+    pub seed: u64,
+    pub hand_scores: [[i32; 2]; 2],
+}
+
+impl BoardResult {
+    /// How many points a hand the first configuration's partnership beat
+    /// the second's by, averaged over the two seatings.
+    pub fn differential(&self) -> f64 {
+        let [first, second] = self.hand_scores;
+        ((first[0] - first[1]) + (second[0] - second[1])) as f64 / 2.0
+    }
+}
+
+impl Tournament {
+    /// Plays every board between `configs`, in board order whatever the
+    /// number of threads.
+    pub fn run(&self, configs: [&BotConfig; 2]) -> TournamentReport {
+        let next = AtomicUsize::new(0);
+        let mut boards: Vec<BoardResult> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.threads.clamp(1, self.boards.max(1)))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = vec![];
+                        loop {
+                            let board = next.fetch_add(1, atomic::Ordering::Relaxed);
+                            if board >= self.boards {
+                                return results;
+                            }
+                            results.push(self.play_board(board, configs));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("tournament worker panicked"))
+                .collect()
+        });
+        boards.sort_by_key(|board| board.seed);
+        TournamentReport { boards }
+    }
+
+    /// Plays the hand dealt for `board` in both seatings.
+    fn play_board(&self, board: usize, configs: [&BotConfig; 2]) -> BoardResult {
+        let seed = self.seed + board as u64;
+        let first_bidder = Player::try_from(board % 4).unwrap();
+        let mut hand_scores = [[0; 2]; 2];
+        for (seating, scores) in hand_scores.iter_mut().enumerate() {
+            let rng = StdRng::seed_from_u64(seed);
+            let mut game = Game::with_first_bidder(rng, self.rules, first_bidder)
+                .expect("the tournament's rules can be played");
+            let mut rng = StdRng::seed_from_u64(seed);
+            while game.rounds().is_empty() {
+                let action = all::<Player>()
+                    .find_map(|player| {
+                        let config = configs[(player as usize + seating) % 2];
+                        Bot::decide(&PlayerView::new(&game, player), config, &mut rng)
+                    })
+                    .expect("someone can act");
+                game.act(action).expect("the bot only takes legal actions");
+            }
+            let hand = game.rounds()[0].scores;
+            *scores = [hand[seating], hand[1 - seating]];
+        }
+        BoardResult { seed, hand_scores }
+    }
+}
+
+/// What came of a tournament, from the first configuration's side.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TournamentReport {
+    pub boards: Vec<BoardResult>,
+}
+
+impl TournamentReport {
+    fn count(&self, keep: impl Fn(f64) -> bool) -> usize {
+        self.boards
+            .iter()
+            .filter(|board| keep(board.differential()))
+            .count()
+    }
+
+    pub fn wins(&self) -> usize {
+        self.count(|differential| differential > 0.0)
+    }
+
+    pub fn losses(&self) -> usize {
+        self.count(|differential| differential < 0.0)
+    }
+
+    pub fn ties(&self) -> usize {
+        self.count(|differential| differential == 0.0)
+    }
+
+    /// The share of boards won, with ties counting half.
+    pub fn win_rate(&self) -> f64 {
+        let boards = self.boards.len().max(1) as f64;
+        (self.wins() as f64 + self.ties() as f64 / 2.0) / boards
+    }
+
+    /// A 95% confidence interval for the win rate.
+    pub fn win_rate_interval(&self) -> (f64, f64) {
+        let rate = self.win_rate();
+        let spread = Z_95 * (rate * (1.0 - rate) / self.boards.len().max(1) as f64).sqrt();
+        ((rate - spread).max(0.0), (rate + spread).min(1.0))
+    }
+
+    pub fn mean_differential(&self) -> f64 {
+        let total: f64 = self.boards.iter().map(BoardResult::differential).sum();
+        total / self.boards.len().max(1) as f64
+    }
+
+    /// A 95% confidence interval for the mean differential.
+    pub fn differential_interval(&self) -> (f64, f64) {
+        let mean = self.mean_differential();
+        let boards = self.boards.len();
+        if boards < 2 {
+            return (mean, mean);
+        }
+        let variance = self
+            .boards
+            .iter()
+            .map(|board| (board.differential() - mean).powi(2))
+            .sum::<f64>()
+            / (boards - 1) as f64;
+        let spread = Z_95 * (variance / boards as f64).sqrt();
+        (mean - spread, mean + spread)
+    }
+
+    /// How much stronger the first configuration is than the second in Elo,
+    /// going by the win rate.
+    pub fn elo(&self) -> f64 {
+        elo(self.win_rate())
+    }
+
+    /// A 95% confidence interval for the Elo difference.
+    pub fn elo_interval(&self) -> (f64, f64) {
+        let (low, high) = self.win_rate_interval();
+        (elo(low), elo(high))
+    }
+}
+
+/// The Elo difference at which the stronger side is expected to score
+/// `win_rate`.
+fn elo(win_rate: f64) -> f64 {
+    let win_rate = win_rate.clamp(WIN_RATE_CLAMP, 1.0 - WIN_RATE_CLAMP);
+    -400.0 * (1.0 / win_rate - 1.0).log10()
+}
+
+impl Display for TournamentReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (rate_low, rate_high) = self.win_rate_interval();
+        let (diff_low, diff_high) = self.differential_interval();
+        let (elo_low, elo_high) = self.elo_interval();
+        writeln!(
+            f,
+            "{} boards of one hand: {} won, {} lost, {} tied",
+            self.boards.len(),
+            self.wins(),
+            self.losses(),
+            self.ties()
+        )?;
+        writeln!(
+            f,
+            "win rate {:.1}% (95% CI {:.1}% to {:.1}%)",
+            self.win_rate() * 100.0,
+            rate_low * 100.0,
+            rate_high * 100.0
+        )?;
+        writeln!(
+            f,
+            "score differential {:+.1} a hand (95% CI {:+.1} to {:+.1})",
+            self.mean_differential(),
+            diff_low,
+            diff_high
+        )?;
+        write!(
+            f,
+            "Elo {:+.0} (95% CI {:+.0} to {:+.0})",
+            self.elo(),
+            elo_low,
+            elo_high
+        )
+    }
+}
+
+#[test]
+fn test_tournament() {
+    let weak = BotConfig {
+        max_iterations: Some(20),
+        solve_below: 4,
+        solver_samples: 2,
+        enumerate_below: 4,
+        threads: 1,
+        bidding_samples: 2,
+        ..Default::default()
+    };
+    let tournament = Tournament {
+        boards: 3,
+        seed: 7,
+        threads: 2,
+        ..Default::default()
+    };
+    let report = tournament.run([&weak, &weak]);
+    assert_eq!(
+        report
+            .boards
+            .iter()
+            .map(|board| board.seed)
+            .collect::<Vec<_>>(),
+        [7, 8, 9]
+    );
+    assert_eq!(report.wins() + report.losses() + report.ties(), 3);
+    let (low, high) = report.differential_interval();
+    assert!(low <= report.mean_differential() && report.mean_differential() <= high);
+    // The same seeds give the same boards, however they're spread over
+    // threads.
+    let single = Tournament {
+        threads: 1,
+        ..tournament
+    };
+    assert_eq!(single.run([&weak, &weak]), report);
+
+    assert_eq!(elo(0.5), 0.0);
+    assert!((elo(10.0 / 11.0) - 400.0).abs() < 1e-9);
+}
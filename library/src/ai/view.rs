@@ -0,0 +1,194 @@
+use super::passing::indices_of;
+use super::{first_legal, Bot, BotConfig, Inconsistency};
+use crate::rules::Rules;
+use crate::{meld_cards, partner, Action, Card, Game, Phase, Player, PlayingPhase, Trick};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Everything one player has been able to see of the hand in progress.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerView {
+    pub player: Player,
+    pub hand: Vec<Card>,
+    pub phase: Phase,
+    pub current_player: Player,
+    pub rules: Rules,
+    pub first_bidder: Player,
+    /// Every bid of the hand so far, starting with the first bidder's, where
+    /// 0 is a pass.
+    pub bids: Vec<i32>,
+    /// The passes the player sent or got, with who sent them.
+    pub passes: Vec<(Player, Vec<Card>)>,
+    /// The cards each player showed as meld.
+    pub reveals: [Option<Vec<Card>>; 4],
+    /// Every card played so far, in order.
+    pub plays: Vec<(Player, Card)>,
+}
+
+impl PlayerView {
+    pub fn new<R: Rng>(game: &Game<R>, player: Player) -> Self {
+        let log = &game.hand.log;
+        Self {
+            player,
+            hand: game.player_hand(player),
+            phase: game.phase().clone(),
+            current_player: game.current_player(),
+            rules: *game.rules(),
+            first_bidder: game.first_bidder(),
+            bids: log.bids.clone(),
+            passes: log
+                .passes
+                .iter()
+                .filter(|(from, _)| *from == player || partner(*from) == player)
+                .cloned()
+                .collect(),
+            reveals: log.reveals.clone(),
+            plays: log.plays.clone(),
+        }
+    }
+}
+
+impl Bot {
+    /// A bot that has seen the play so far from `view`'s seat, or why the
+    /// plays in `view` can't have happened. `None` before the play has
+    /// started.
+    pub fn from_view(view: &PlayerView, config: BotConfig) -> Option<Result<Self, Inconsistency>> {
+        let Phase::Play(phase) = &view.phase else {
+            return None;
+        };
+        let mut hand = view.hand.clone();
+        hand.extend(
+            view.plays
+                .iter()
+                .filter(|(player, _)| *player == view.player)
+                .map(|(_, card)| *card),
+        );
+        let start = PlayingPhase {
+            piles: Default::default(),
+            trick: Trick {
+                first_player: phase.bid_winner,
+                cards: vec![],
+            },
+            ..phase.clone()
+        };
+
+        let mut bot = Bot::with_config(view.player, hand, start, config);
+        bot.observe_bidding(view.first_bidder, &view.bids);
+        for (from, cards) in &view.passes {
+            bot.observe_pass(*from, cards, &view.rules);
+        }
+        bot.observe_reveals(&view.reveals);
+        for (player, card) in &view.plays {
+            let stack = bot.playing_phase.trick.cards.clone();
+            if let Err(inconsistency) = bot.update(*player, *card, phase.trump, &stack) {
+                return Some(Err(inconsistency));
+            }
+        }
+        Some(Ok(bot))
+    }
+
+    /// What the player in `view`'s seat does next, in any phase, or `None`
+    /// while it's waiting on someone else.
+    pub fn decide<R>(view: &PlayerView, config: &BotConfig, rng: &mut R) -> Option<Action>
+    where
+        R: Rng + ?Sized,
+    {
+        let player = view.player;
+        let hand = &view.hand;
+        let rules = &view.rules;
+        Some(match &view.phase {
+            // Everyone reviews the meld at once, whoever's turn it is.
+            Phase::ReviewingRevealedCards { reviews, .. } => {
+                return (!reviews[player as usize]).then_some(Action::Continue(player));
+            }
+            _ if view.current_player != player => return None,
+            Phase::Bidding { first_bidder, bids } => Action::Bid(Bot::choose_bid(
+                player,
+                hand,
+                *first_bidder,
+                bids,
+                rules,
+                config,
+                rng,
+            )),
+            Phase::DeclareTrump { .. } => Bot::choose_trump(player, hand, rules, config, rng).0,
+            Phase::PassingTo { trump, .. } => Bot::choose_pass(hand, *trump, true, rules),
+            Phase::PassingBack { trump, .. } => Bot::choose_pass(hand, *trump, false, rules),
+            Phase::RevealingCards { trump, .. } => {
+                Action::ShowPoints(indices_of(hand, &meld_cards(hand, *trump)))
+            }
+            Phase::Play(phase) => {
+                // A view the bot can't make sense of still gets a legal card.
+                let card = match Bot::from_view(view, *config)? {
+                    Ok(bot) => bot.get_move_with_rng(rng),
+                    Err(_) => first_legal(hand, phase)?,
+                };
+                Action::Play(hand.iter().position(|c| *c == card)?)
+            }
+        })
+    }
+}
+
+/// Four bots play whole hands from many deals, to check every phase gets an
+/// action the game accepts.
+#[test]
+fn test_bots_play_whole_games() {
+    use enum_iterator::all;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let config = BotConfig {
+        max_iterations: Some(20),
+        solve_below: 4,
+        solver_samples: 2,
+        enumerate_below: 4,
+        threads: 1,
+        bidding_samples: 2,
+        ..Default::default()
+    };
+    for seed in 0..6 {
+        let mut game = Game::new(StdRng::seed_from_u64(seed));
+        let mut rng = StdRng::seed_from_u64(seed);
+        while game.rounds().len() < 2 {
+            let action = all::<Player>()
+                .find_map(|player| Bot::decide(&PlayerView::new(&game, player), &config, &mut rng))
+                .expect("someone can act");
+            game.act(action.clone())
+                .unwrap_or_else(|error| panic!("seed {seed}: {action:?} failed with {error:?}"));
+        }
+    }
+}
+
+/// Plays that can't have happened are reported instead of panicking, and
+/// the bot still finds a legal card.
+#[test]
+fn test_impossible_plays() {
+    use enum_iterator::all;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let config = BotConfig {
+        max_iterations: Some(20),
+        threads: 1,
+        bidding_samples: 2,
+        ..Default::default()
+    };
+    let mut game = Game::new(StdRng::seed_from_u64(1));
+    let mut rng = StdRng::seed_from_u64(1);
+    while !matches!(game.phase(), Phase::Play(_)) {
+        let action = all::<Player>()
+            .find_map(|player| Bot::decide(&PlayerView::new(&game, player), &config, &mut rng))
+            .expect("someone can act");
+        game.act(action).unwrap();
+    }
+    let player = game.current_player();
+    let mut view = PlayerView::new(&game, player);
+    let card = game.player_hand(partner(player))[0];
+    view.plays = vec![(partner(player), card); 13];
+    assert!(matches!(
+        Bot::from_view(&view, config),
+        Some(Err(Inconsistency::EmptyHand(_)))
+    ));
+    let action = Bot::decide(&view, &config, &mut rng).unwrap();
+    game.act(action).unwrap();
+}
@@ -7,8 +7,12 @@ use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
 use std::fmt::Display;
+use std::str::FromStr;
+
+use rules::{CounterScheme, Rules, SetPenalty};
 
 pub mod ai;
+pub mod rules;
 
 #[derive(
     Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Sequence, Serialize, Deserialize, Hash,
@@ -22,19 +26,6 @@ pub enum Rank {
     Ace,
 }
 
-impl Rank {
-    fn points(self) -> i32 {
-        match self {
-            Rank::Nine => 0,
-            Rank::Jack => 0,
-            Rank::Queen => 5,
-            Rank::King => 5,
-            Rank::Ten => 10,
-            Rank::Ace => 10,
-        }
-    }
-}
-
 impl Display for Rank {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -77,6 +68,21 @@ impl Display for Suit {
     }
 }
 
+/// Reads a suit written as its letter, like `H`, or its symbol, like `♥`.
+impl FromStr for Suit {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "D" | "♦" => Ok(Suit::Diamonds),
+            "C" | "♣" => Ok(Suit::Clubs),
+            "H" | "♥" => Ok(Suit::Hearts),
+            "S" | "♠" => Ok(Suit::Spades),
+            _ => Err(()),
+        }
+    }
+}
+
 impl TryFrom<usize> for Suit {
     type Error = ();
 
@@ -102,6 +108,25 @@ impl Display for Card {
     }
 }
 
+/// Reads a card written as its rank then its suit, like `TH` or `T♥`.
+impl FromStr for Card {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let rank = match chars.next() {
+            Some('9') => Rank::Nine,
+            Some('J') => Rank::Jack,
+            Some('Q') => Rank::Queen,
+            Some('K') => Rank::King,
+            Some('T') => Rank::Ten,
+            Some('A') => Rank::Ace,
+            _ => return Err(()),
+        };
+        Ok(Card(chars.as_str().parse()?, rank))
+    }
+}
+
 fn shuffled<R>(rng: &mut R) -> Vec<Card>
 where
     R: Rng + ?Sized,
@@ -129,6 +154,10 @@ pub struct Game<R: Rng> {
     hand: RoundState,
     scores: [i32; 2],
     first_bidder: Player,
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    rounds: Vec<RoundSummary>,
 }
 
 impl Default for Game<ThreadRng> {
@@ -138,22 +167,45 @@ impl Default for Game<ThreadRng> {
 }
 
 impl<R: Rng> Game<R> {
-    pub fn new(mut rng: R) -> Self {
-        Self {
-            hand: RoundState::start(&mut rng, Player::A),
+    pub fn new(rng: R) -> Self {
+        Self::with_rules(rng, Rules::default()).expect("the default rules can be played")
+    }
+
+    /// A game played under `rules`, unless they can't be played.
+    pub fn with_rules(rng: R, rules: Rules) -> Result<Self, Error> {
+        Self::with_first_bidder(rng, rules, Player::A)
+    }
+
+    /// A game where `first_bidder` bids first in the first hand.
+    pub fn with_first_bidder(
+        mut rng: R,
+        rules: Rules,
+        first_bidder: Player,
+    ) -> Result<Self, Error> {
+        rules.validate()?;
+        Ok(Self {
+            hand: RoundState::start(&mut rng, first_bidder, rules),
             rng,
-            first_bidder: Player::A,
+            first_bidder,
             scores: [0; 2],
-        }
+            rules,
+            rounds: vec![],
+        })
     }
 
     pub fn act(&mut self, action: Action) -> Result<(), Error> {
+        if let Action::Redeal(player) = action {
+            self.hand.claim_misdeal(player)?;
+            self.hand = RoundState::start(&mut self.rng, self.first_bidder, self.rules);
+            return Ok(());
+        }
         let result = self.hand.act(action)?;
-        if let Some((a, b)) = result {
-            self.scores[0] += a;
-            self.scores[1] += b;
+        if let Some(summary) = result {
+            self.scores[0] += summary.scores[0];
+            self.scores[1] += summary.scores[1];
+            self.rounds.push(summary);
             self.first_bidder = next_cycle(&self.first_bidder).unwrap();
-            self.hand = RoundState::start(&mut self.rng, self.first_bidder);
+            self.hand = RoundState::start(&mut self.rng, self.first_bidder, self.rules);
         }
         Ok(())
     }
@@ -176,6 +228,27 @@ impl<R: Rng> Game<R> {
     pub fn scores(&self) -> [i32; 2] {
         self.scores
     }
+
+    /// The suits the bid winner may currently name as trump. This is empty
+    /// outside of [`Phase::DeclareTrump`], and also when the bid winner has
+    /// to throw the hand in.
+    pub fn legal_trumps(&self) -> Vec<Suit> {
+        self.hand.legal_trumps()
+    }
+
+    /// Whether `player` was dealt a hand they may currently claim a redeal for.
+    pub fn can_claim_misdeal(&self, player: Player) -> bool {
+        self.hand.claim_misdeal(player).is_ok()
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// How each finished hand of this game was scored, oldest first.
+    pub fn rounds(&self) -> &[RoundSummary] {
+        &self.rounds
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -183,6 +256,21 @@ struct RoundState {
     current_player: Player,
     hands: [Vec<Card>; 4],
     phase: Phase,
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    misdealt: [bool; 4],
+    #[serde(default)]
+    log: RoundLog,
+}
+
+/// What has happened so far in a hand, which the phase alone doesn't keep.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RoundLog {
+    bids: Vec<i32>,
+    passes: Vec<(Player, Vec<Card>)>,
+    reveals: [Option<Vec<Card>>; 4],
+    plays: Vec<(Player, Card)>,
 }
 
 impl Display for RoundState {
@@ -193,31 +281,43 @@ impl Display for RoundState {
             for card in hand {
                 write!(f, "{card} ")?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         Ok(())
     }
 }
 
 impl RoundState {
-    pub fn start<R>(rng: &mut R, player: Player) -> Self
+    pub fn start<R>(rng: &mut R, player: Player, rules: Rules) -> Self
     where
         R: Rng + ?Sized,
     {
+        let hands = dealt(shuffled(rng));
         Self {
             current_player: player,
-            hands: dealt(shuffled(rng)),
+            misdealt: [0, 1, 2, 3].map(|i| rules.misdeal.is_misdeal(&hands[i], rules.counters)),
+            hands,
             phase: Phase::Bidding {
                 first_bidder: player,
                 bids: vec![],
             },
+            rules,
+            log: Default::default(),
+        }
+    }
+
+    fn claim_misdeal(&self, player: Player) -> Result<(), Error> {
+        match self.phase {
+            Phase::Bidding { .. } if self.misdealt[player as usize] => Ok(()),
+            Phase::Bidding { .. } => Err(Error::NotAMisdeal),
+            _ => Err(Error::IncorrectAction),
         }
     }
 }
 
 fn is_legal_play(pile: &[Card], hand: &[Card], card: Card, trump: Suit) -> bool {
     // if there isn't a card played, anything is legal
-    if let Some(first_card) = pile.get(0) {
+    if let Some(first_card) = pile.first() {
         let starting_suit = first_card.0;
         // if the card doesn't match the starting suit
         let suitwise_legal = if card.0 != starting_suit {
@@ -384,6 +484,19 @@ fn compare(a: Card, b: Card, trump: Suit, lead: Suit) -> Ordering {
     }
 }
 
+/// The index of the card that takes `cards`, a trick in the order played:
+/// the first of the highest.
+pub(crate) fn trick_winner(cards: &[Card], trump: Suit) -> usize {
+    let lead = cards[0].0;
+    let mut winner = 0;
+    for (i, card) in cards.iter().enumerate().skip(1) {
+        if compare(*card, cards[winner], trump, lead).is_gt() {
+            winner = i;
+        }
+    }
+    winner
+}
+
 #[test]
 fn test_compare() {
     let ah = Card(Suit::Hearts, Rank::Ace);
@@ -401,11 +514,23 @@ fn test_compare() {
     assert_eq!(compare(kh, ac, Suit::Clubs, Suit::Hearts), Ordering::Less);
 }
 
+#[test]
+fn test_trick_winner() {
+    let ah = Card(Suit::Hearts, Rank::Ace);
+    let kh = Card(Suit::Hearts, Rank::King);
+    let ac = Card(Suit::Clubs, Rank::Ace);
+    let nc = Card(Suit::Clubs, Rank::Nine);
+
+    assert_eq!(trick_winner(&[kh, ah, ac, ah], Suit::Diamonds), 1);
+    assert_eq!(trick_winner(&[kh, ah, nc, ac], Suit::Clubs), 3);
+}
+
 impl RoundState {
-    fn act(&mut self, action: Action) -> Result<Option<(i32, i32)>, Error> {
+    fn act(&mut self, action: Action) -> Result<Option<RoundSummary>, Error> {
         match (&mut self.phase, action) {
             (Phase::Bidding { bids, first_bidder }, Action::Bid(amount)) => {
                 bids.push(amount);
+                self.log.bids.push(amount);
                 self.current_player = next_cycle(&self.current_player).unwrap();
                 if bids.len() == 4 {
                     let (highest_bid, winning_bidder) = bids
@@ -428,11 +553,21 @@ impl RoundState {
                 },
                 Action::DeclareSuit(suit),
             ) => {
-                self.current_player = partner(*bid_winner);
-                self.phase = Phase::PassingTo {
-                    bid_winner: *bid_winner,
-                    highest_bid: *highest_bid,
-                    trump: suit,
+                if self.rules.trump_requires_marriage
+                    && !has_marriage(&self.hands[*bid_winner as usize], suit)
+                {
+                    return Err(Error::NoMarriageInTrump);
+                }
+                if self.rules.passing.count == 0 {
+                    self.current_player = *bid_winner;
+                    self.phase = Self::revealing(*bid_winner, *highest_bid, suit);
+                } else {
+                    self.current_player = partner(*bid_winner);
+                    self.phase = Phase::PassingTo {
+                        bid_winner: *bid_winner,
+                        highest_bid: *highest_bid,
+                        trump: suit,
+                    }
                 }
             }
             (
@@ -443,7 +578,14 @@ impl RoundState {
                 },
                 Action::Pass(indices),
             ) => {
-                Self::pass_cards(&mut self.hands, self.current_player, indices)?;
+                let passed = Self::pass_cards(
+                    &mut self.hands,
+                    self.current_player,
+                    indices,
+                    self.rules.passing.count,
+                    Some(*trump).filter(|_| self.rules.passing.partner_passes_trump),
+                )?;
+                self.log.passes.push((self.current_player, passed));
                 self.current_player = *bid_winner;
                 self.phase = Phase::PassingBack {
                     trump: *trump,
@@ -459,15 +601,16 @@ impl RoundState {
                 },
                 Action::Pass(indices),
             ) => {
-                Self::pass_cards(&mut self.hands, self.current_player, indices)?;
+                let passed = Self::pass_cards(
+                    &mut self.hands,
+                    self.current_player,
+                    indices,
+                    self.rules.passing.count,
+                    None,
+                )?;
+                self.log.passes.push((self.current_player, passed));
                 self.current_player = *bid_winner;
-                self.phase = Phase::RevealingCards {
-                    extra_points: [0, 0],
-                    reveals: Default::default(),
-                    trump: *trump,
-                    highest_bid: *highest_bid,
-                    bid_winner: *bid_winner,
-                };
+                self.phase = Self::revealing(*bid_winner, *highest_bid, *trump);
             }
             (
                 Phase::RevealingCards {
@@ -479,12 +622,13 @@ impl RoundState {
                 },
                 Action::ShowPoints(cards),
             ) => {
-                let the_cards = cards
+                let the_cards: Vec<Card> = cards
                     .into_iter()
                     .filter_map(|x| self.hands[self.current_player as usize].get(x))
-                    .map(|x| *x)
+                    .copied()
                     .collect();
                 extra_points[self.current_player as usize % 2] += bonus_points(&the_cards, *trump);
+                self.log.reveals[self.current_player as usize] = Some(the_cards.clone());
                 reveals[self.current_player as usize] = Some(the_cards);
                 self.current_player = next_cycle(&self.current_player).unwrap();
                 if reveals.iter().filter(|x| x.is_some()).count() == 4 {
@@ -518,6 +662,8 @@ impl RoundState {
                         bid_winner: *bid_winner,
                         highest_bid: *highest_bid,
                         extra_points: *extra_points,
+                        counters: self.rules.counters,
+                        set_penalty: self.rules.set_penalty,
                         piles: [vec![], vec![]],
                         trick: Trick {
                             first_player: *bid_winner,
@@ -535,113 +681,333 @@ impl RoundState {
                 let card = current_hand[index];
 
                 let (next_player, res) =
-                    playing_phase.play(self.current_player, &current_hand, card)?;
+                    playing_phase.play(self.current_player, current_hand, card)?;
 
                 current_hand.remove(index);
+                self.log.plays.push((self.current_player, card));
 
                 self.current_player = next_player;
                 return Ok(res);
             }
+            (
+                Phase::DeclareTrump {
+                    bid_winner,
+                    highest_bid,
+                },
+                Action::ThrowIn,
+            ) => {
+                let (bid_winner, highest_bid) = (*bid_winner, *highest_bid);
+                if !self.legal_trumps().is_empty() {
+                    return Err(Error::CannotThrowIn);
+                }
+                return Ok(Some(Self::thrown_in(&self.rules, bid_winner, highest_bid)));
+            }
             _ => return Err(Error::IncorrectAction),
         }
         Ok(None)
     }
 
+    /// The bid winner couldn't name a trump, so their team is set and nobody
+    /// scores anything else.
+    fn thrown_in(rules: &Rules, bid_winner: Player, highest_bid: i32) -> RoundSummary {
+        let mut scores = [0; 2];
+        scores[bid_winner as usize % 2] = rules.set_penalty.set_score(highest_bid, 0);
+        RoundSummary {
+            bid_winner,
+            highest_bid,
+            counters: rules.counters,
+            set_penalty: rules.set_penalty,
+            outcome: BidOutcome::ThrownIn,
+            extra_points: [0; 2],
+            trick_points: [0; 2],
+            scores,
+        }
+    }
+
+    fn legal_trumps(&self) -> Vec<Suit> {
+        match self.phase {
+            Phase::DeclareTrump { bid_winner, .. } if self.rules.trump_requires_marriage => {
+                let hand = &self.hands[bid_winner as usize];
+                all::<Suit>()
+                    .filter(|suit| has_marriage(hand, *suit))
+                    .collect()
+            }
+            Phase::DeclareTrump { .. } => all::<Suit>().collect(),
+            _ => vec![],
+        }
+    }
+
+    fn revealing(bid_winner: Player, highest_bid: i32, trump: Suit) -> Phase {
+        Phase::RevealingCards {
+            extra_points: [0, 0],
+            reveals: Default::default(),
+            trump,
+            highest_bid,
+            bid_winner,
+        }
+    }
+
+    /// Moves the cards at `indices` to the passer's partner. If `required_suit`
+    /// is set and the passer holds any of it, at least one such card must go.
     fn pass_cards(
         hands: &mut [Vec<Card>; 4],
         current_player: Player,
         indices: Vec<usize>,
-    ) -> Result<(), Error> {
+        count: usize,
+        required_suit: Option<Suit>,
+    ) -> Result<Vec<Card>, Error> {
         let indices: BTreeSet<_> = indices.into_iter().collect();
-        if indices.len() != 4 {
+        if indices.len() != count {
             return Err(Error::PassingWrongNumberOfCards);
         }
-        let taken_cards = take_indices(&mut hands[current_player as usize], indices)?;
-        hands[partner(current_player) as usize].extend(taken_cards);
-        Ok(())
+        let hand = &mut hands[current_player as usize];
+        if let Some(suit) = required_suit {
+            let holds_suit = hand.iter().any(|c| c.0 == suit);
+            let passes_suit = indices
+                .iter()
+                .any(|index| hand.get(*index).is_some_and(|c| c.0 == suit));
+            if holds_suit && !passes_suit {
+                return Err(Error::PassMustIncludeTrump);
+            }
+        }
+        let taken_cards = take_indices(hand, indices)?;
+        hands[partner(current_player) as usize].extend(&taken_cards);
+        Ok(taken_cards)
     }
 }
 
-fn bonus_points(cards: &Vec<Card>, trump: Suit) -> i32 {
-    fn marriage(cards: &Vec<Card>, suit: Suit) -> i32 {
-        or_double(
-            cards,
-            vec![Card(suit, Rank::King), Card(suit, Rank::Queen)],
-            20,
-            40,
-        )
+#[test]
+fn test_configurable_passing() {
+    use crate::rules::PassingRules;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let rules = Rules {
+        passing: PassingRules {
+            count: 3,
+            partner_passes_trump: true,
+        },
+        ..Default::default()
+    };
+    let mut game = Game::with_rules(StdRng::seed_from_u64(0), rules).unwrap();
+    for bid in [250, 0, 0, 0] {
+        game.act(Action::Bid(bid)).unwrap();
     }
+    game.act(Action::DeclareSuit(Suit::Spades)).unwrap();
+    assert_eq!(game.current_player(), Player::C);
 
-    fn or_double(reveal: &Vec<Card>, pattern: Vec<Card>, points: i32, double: i32) -> i32 {
-        let mut counts = vec![0; pattern.len()];
-        for needle in reveal {
-            if let Some(index) = pattern.iter().position(|card| needle == card) {
-                counts[index] += 1
-            }
-        }
-        if counts.iter().all(|x| *x >= 2) {
-            double
-        } else if counts.iter().all(|x| *x >= 1) {
-            points
-        } else {
-            0
-        }
+    let hand = game.player_hand(Player::C);
+    let (trump, other): (Vec<usize>, Vec<usize>) =
+        (0..hand.len()).partition(|i| hand[*i].0 == Suit::Spades);
+    assert!(!trump.is_empty() && other.len() >= 3);
+    assert_eq!(
+        game.act(Action::Pass(other[..3].to_vec())),
+        Err(Error::PassMustIncludeTrump)
+    );
+    assert_eq!(
+        game.act(Action::Pass(vec![trump[0], other[0], other[1], other[2]])),
+        Err(Error::PassingWrongNumberOfCards)
+    );
+    game.act(Action::Pass(vec![trump[0], other[0], other[1]]))
+        .unwrap();
+    assert_eq!(game.player_hand(Player::A).len(), 15);
+
+    let mut bits = BitVec::new();
+    Action::Pass(vec![0, 1, 14]).encode(&mut bits, &game);
+    assert_eq!(bits.len(), 15);
+    let (rest, decoded) = Action::decode(&bits, &game).unwrap();
+    assert!(rest.is_none());
+    assert!(matches!(decoded, Action::Pass(cards) if cards == vec![0, 1, 14]));
+}
+
+#[test]
+fn test_trump_requires_marriage() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use Rank::*;
+    use Suit::*;
+
+    let rules = Rules {
+        trump_requires_marriage: true,
+        ..Default::default()
+    };
+    let mut game = Game::with_rules(StdRng::seed_from_u64(0), rules).unwrap();
+    for bid in [300, 0, 0, 0] {
+        game.act(Action::Bid(bid)).unwrap();
     }
+    game.hand.hands[0] = vec![
+        Card(Hearts, King),
+        Card(Hearts, Queen),
+        Card(Spades, King),
+        Card(Clubs, Queen),
+    ];
+    assert_eq!(game.legal_trumps(), vec![Hearts]);
+    assert_eq!(
+        game.act(Action::DeclareSuit(Spades)),
+        Err(Error::NoMarriageInTrump)
+    );
+    game.act(Action::DeclareSuit(Hearts)).unwrap();
+    assert!(matches!(game.phase(), Phase::PassingTo { .. }));
+    assert!(game.legal_trumps().is_empty());
+}
 
-    fn round(reveal: &Vec<Card>, rank: Rank, points: i32) -> i32 {
-        or_double(
-            reveal,
-            all::<Suit>().map(|suit| Card(suit, rank)).collect(),
-            points,
-            points * 10,
-        )
+#[test]
+fn test_throw_in_without_marriage() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use Rank::*;
+    use Suit::*;
+
+    let rules = Rules {
+        trump_requires_marriage: true,
+        ..Default::default()
+    };
+    let mut game = Game::with_rules(StdRng::seed_from_u64(0), rules).unwrap();
+    for bid in [0, 275, 0, 0] {
+        game.act(Action::Bid(bid)).unwrap();
+    }
+    game.hand.hands[1] = vec![Card(Hearts, King), Card(Hearts, Queen)];
+    assert_eq!(game.act(Action::ThrowIn), Err(Error::CannotThrowIn));
+    game.hand.hands[1] = vec![Card(Hearts, King), Card(Spades, Queen)];
+    assert!(game.legal_trumps().is_empty());
+    assert_eq!(
+        game.act(Action::DeclareSuit(Hearts)),
+        Err(Error::NoMarriageInTrump)
+    );
+    let mut bits = BitVec::new();
+    Action::ThrowIn.encode(&mut bits, &game);
+    assert!(matches!(
+        Action::decode(bits.as_bitslice(), &game),
+        Some((None, Action::ThrowIn))
+    ));
+    game.act(Action::ThrowIn).unwrap();
+    assert_eq!(game.scores(), [0, -275]);
+    assert_eq!(game.rounds().len(), 1);
+    assert!(matches!(game.phase(), Phase::Bidding { .. }));
+}
+
+#[test]
+fn test_redeal() {
+    use crate::rules::MisdealRules;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let rules = Rules {
+        misdeal: MisdealRules {
+            nines: Some(0),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let new_game = || Game::with_rules(StdRng::seed_from_u64(0), rules).unwrap();
+    let mut game = new_game();
+    game.act(Action::Bid(250)).unwrap();
+    let hand = game.player_hand(Player::C);
+    assert!(game.can_claim_misdeal(Player::C));
+    game.act(Action::Redeal(Player::C)).unwrap();
+    assert_ne!(game.player_hand(Player::C), hand);
+    assert_eq!(game.current_player(), Player::A);
+    assert!(matches!(game.phase(), Phase::Bidding { bids, .. } if bids.is_empty()));
+
+    let mut bits = BitVec::new();
+    let mut replayed = new_game();
+    for action in [Action::Bid(250), Action::Redeal(Player::C)] {
+        action.encode(&mut bits, &replayed);
+        replayed.act(action).unwrap();
     }
+    assert_eq!(replayed.player_hand(Player::C), game.player_hand(Player::C));
+
+    let mut decoded = new_game();
+    let mut rest = Some(bits.as_bitslice());
+    while let Some(bits) = rest {
+        let (next, action) = Action::decode(bits, &decoded).unwrap();
+        decoded.act(action).unwrap();
+        rest = next;
+    }
+    assert_eq!(decoded.player_hand(Player::C), game.player_hand(Player::C));
+
+    let mut game = Game::new(StdRng::seed_from_u64(0));
+    assert_eq!(game.act(Action::Redeal(Player::A)), Err(Error::NotAMisdeal));
+}
+
+#[test]
+fn test_no_passing() {
+    use crate::rules::PassingRules;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let rules = Rules {
+        passing: PassingRules {
+            count: 0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut game = Game::with_rules(StdRng::seed_from_u64(0), rules).unwrap();
+    for bid in [0, 250, 0, 0] {
+        game.act(Action::Bid(bid)).unwrap();
+    }
+    game.act(Action::DeclareSuit(Suit::Hearts)).unwrap();
+    assert!(matches!(game.phase(), Phase::RevealingCards { .. }));
+    assert_eq!(game.current_player(), Player::B);
+}
+
+fn has_marriage(cards: &[Card], suit: Suit) -> bool {
+    cards.contains(&Card(suit, Rank::King)) && cards.contains(&Card(suit, Rank::Queen))
+}
+
+fn bonus_points(cards: &[Card], trump: Suit) -> i32 {
+    let mut counts = [[0u8; 6]; 4];
+    for card in cards {
+        counts[card.0 as usize][card.1 as usize] += 1;
+    }
+
+    let or_double = |pattern: &[Card], points: i32, double: i32| -> i32 {
+        let fewest = pattern
+            .iter()
+            .map(|card| counts[card.0 as usize][card.1 as usize])
+            .min()
+            .unwrap_or(0);
+        match fewest {
+            0 => 0,
+            1 => points,
+            _ => double,
+        }
+    };
+    let marriage =
+        |suit: Suit| or_double(&[Card(suit, Rank::King), Card(suit, Rank::Queen)], 20, 40);
+    let round = |rank: Rank, points: i32| {
+        let pattern: Vec<Card> = all::<Suit>().map(|suit| Card(suit, rank)).collect();
+        or_double(&pattern, points, points * 10)
+    };
+    let run: Vec<Card> = all::<Rank>()
+        .skip(1)
+        .map(|rank| Card(trump, rank))
+        .collect();
 
     // pinochle
-    or_double(cards, vec![Card(Suit::Spades, Rank::Queen), Card(Suit::Diamonds, Rank::Jack)], 40, 300)  +
+    or_double(&[Card(Suit::Spades, Rank::Queen), Card(Suit::Diamonds, Rank::Jack)], 40, 300)  +
     // run
-    or_double(cards, all::<Rank>().skip(1).map(|rank| Card(trump, rank)).collect(), 150 - 40, 1500 - 80)  +
+    or_double(&run, 150 - 40, 1500 - 80)  +
     // rounds
-    round(cards, Rank::Ace, 100)  +
-    round(cards, Rank::King, 80)  +
-    round(cards, Rank::Queen, 60)  +
-    round(cards, Rank::Jack, 40)  +
+    round(Rank::Ace, 100)  +
+    round(Rank::King, 80)  +
+    round(Rank::Queen, 60)  +
+    round(Rank::Jack, 40)  +
     // trump marriage
-    marriage(cards, trump) +
+    marriage(trump) +
     // other marriages
-    all::<Suit>().map(|suit| marriage(cards, suit)).sum::<i32>() +
+    all::<Suit>().map(marriage).sum::<i32>() +
     // nine of trump
-    or_double(cards, vec![Card(trump, Rank::Nine)], 10, 20)
+    or_double(&[Card(trump, Rank::Nine)], 10, 20)
 }
 
 #[test]
 fn test_bonus_points() {
-    use Rank::*;
     use Suit::*;
 
     fn case(cards: &str, trump: Suit) -> i32 {
-        let cards = cards
-            .split(' ')
-            .map(|x| {
-                let rank = match x.chars().next().unwrap() {
-                    '9' => Nine,
-                    'J' => Jack,
-                    'Q' => Queen,
-                    'K' => King,
-                    'T' => Ten,
-                    'A' => Ace,
-                    _ => todo!(),
-                };
-                let suit = match x.chars().skip(1).next().unwrap() {
-                    'H' => Hearts,
-                    'D' => Diamonds,
-                    'C' => Clubs,
-                    'S' => Spades,
-                    _ => todo!(),
-                };
-                Card(suit, rank)
-            })
-            .collect();
+        let cards: Vec<Card> = cards.split(' ').map(|card| card.parse().unwrap()).collect();
 
         bonus_points(&cards, trump)
     }
@@ -660,6 +1026,52 @@ fn test_bonus_points() {
     assert_eq!(case("KD QD KD QD TD AD JD 9D", Diamonds), 200);
     assert_eq!(case("KD QD KS QS KH QH KC QC", Diamonds), 240);
     assert_eq!(case("AC AH AS KD QD TD AD JD 9D", Diamonds), 260);
+    assert_eq!(case("9D 9D", Diamonds), 20);
+    assert_eq!(case("KD QD KD", Diamonds), 40);
+    assert_eq!(case("QS QS JD", Clubs), 40);
+    assert_eq!(case("KS KH KD KC QS JD", Clubs), 140);
+}
+
+/// The fewest of `cards` that still show all of their meld.
+fn meld_cards(cards: &[Card], trump: Suit) -> Vec<Card> {
+    let meld = bonus_points(cards, trump);
+    let mut shown = cards.to_vec();
+    let mut index = 0;
+    while index < shown.len() {
+        let card = shown.remove(index);
+        if bonus_points(&shown, trump) < meld {
+            shown.insert(index, card);
+            index += 1;
+        }
+    }
+    shown
+}
+
+#[test]
+fn test_meld_cards() {
+    use Rank::*;
+    use Suit::*;
+
+    let cards = vec![
+        Card(Hearts, King),
+        Card(Clubs, Nine),
+        Card(Hearts, Queen),
+        Card(Spades, Queen),
+        Card(Diamonds, Jack),
+        Card(Diamonds, Nine),
+        Card(Clubs, Ace),
+    ];
+    assert_eq!(
+        meld_cards(&cards, Clubs),
+        vec![
+            Card(Hearts, King),
+            Card(Clubs, Nine),
+            Card(Hearts, Queen),
+            Card(Spades, Queen),
+            Card(Diamonds, Jack),
+        ]
+    );
+    assert!(meld_cards(&cards[5..], Hearts).is_empty());
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Sequence, Deserialize, Serialize)]
@@ -684,6 +1096,20 @@ impl TryFrom<usize> for Player {
     }
 }
 
+impl FromStr for Player {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "A" => Ok(Player::A),
+            "B" => Ok(Player::B),
+            "C" => Ok(Player::C),
+            "D" => Ok(Player::D),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Trick {
     pub first_player: Player,
@@ -734,71 +1160,57 @@ pub struct PlayingPhase {
     pub bid_winner: Player,
     pub highest_bid: i32,
     pub extra_points: [i32; 2],
+    #[serde(default)]
+    pub counters: CounterScheme,
+    #[serde(default)]
+    pub set_penalty: SetPenalty,
     pub piles: [Vec<Card>; 2],
     pub trick: Trick,
 }
 
+/// The outcome of a finished hand, per team.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundSummary {
+    pub bid_winner: Player,
+    pub highest_bid: i32,
+    pub counters: CounterScheme,
+    pub set_penalty: SetPenalty,
+    pub outcome: BidOutcome,
+    pub extra_points: [i32; 2],
+    pub trick_points: [i32; 2],
+    pub scores: [i32; 2],
+}
+
+/// Whether the bidding team made its bid, and so which scoring rule applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BidOutcome {
+    Made,
+    Set,
+    /// The bid winner had to throw the hand in before it was played.
+    ThrownIn,
+}
+
 impl PlayingPhase {
     pub fn play(
         &mut self,
         current_player: Player,
         current_hand: &[Card],
         card: Card,
-    ) -> Result<(Player, Option<(i32, i32)>), Error> {
-        if !is_legal_play(&self.trick.cards, &current_hand, card, self.trump) {
+    ) -> Result<(Player, Option<RoundSummary>), Error> {
+        if !is_legal_play(&self.trick.cards, current_hand, card, self.trump) {
             return Err(Error::CardIsNotLegalToPlay);
         }
 
         self.trick.cards.push(card);
         if self.trick.cards.len() == 4 {
-            let player_cards = each_player(self.trick.first_player)
-                .rev()
-                .zip(self.trick.cards.iter().rev());
-            let (winning_player, _) = player_cards
-                .max_by(|(_, a), (_, b)| compare(**a, **b, self.trump, self.trick.cards[0].0))
+            let winning_player = each_player(self.trick.first_player)
+                .nth(trick_winner(&self.trick.cards, self.trump))
                 .unwrap();
-            self.piles[winning_player as usize % 2].extend(self.trick.cards.drain(..));
+            self.piles[winning_player as usize % 2].append(&mut self.trick.cards);
             self.trick.first_player = winning_player;
 
             if current_hand.len() == 1 {
-                fn count(
-                    cards: &Vec<Card>,
-                    last_trick: bool,
-                    extra_points: i32,
-                    highest_bid: i32,
-                    is_bid_winner: bool,
-                ) -> i32 {
-                    let score = cards.iter().map(|Card(_, rank)| rank.points()).sum::<i32>()
-                        + (if last_trick { 10 } else { 0 })
-                        + extra_points;
-
-                    if is_bid_winner {
-                        if score >= highest_bid {
-                            score
-                        } else {
-                            -highest_bid
-                        }
-                    } else {
-                        score
-                    }
-                }
-
-                let a = count(
-                    &self.piles[0],
-                    winning_player as usize % 2 == 0,
-                    self.extra_points[0],
-                    self.highest_bid,
-                    self.bid_winner as usize % 2 == 0,
-                );
-                let b = count(
-                    &self.piles[1],
-                    winning_player as usize % 2 == 1,
-                    self.extra_points[1],
-                    self.highest_bid,
-                    self.bid_winner as usize % 2 == 1,
-                );
-
-                Ok((winning_player, Some((a, b))))
+                Ok((winning_player, Some(self.summarize(winning_player))))
             } else {
                 Ok((winning_player, None))
             }
@@ -806,15 +1218,159 @@ impl PlayingPhase {
             Ok((next_cycle(&current_player).unwrap(), None))
         }
     }
+
+    fn summarize(&self, last_trick_winner: Player) -> RoundSummary {
+        let trick_points = [0, 1].map(|team| {
+            self.piles[team]
+                .iter()
+                .map(|Card(_, rank)| self.counters.points(*rank))
+                .sum::<i32>()
+                + if last_trick_winner as usize % 2 == team {
+                    self.counters.last_trick()
+                } else {
+                    0
+                }
+        });
+        self.summarize_trick_points(trick_points)
+    }
+
+    /// Scores the hand given what each team took in tricks.
+    fn summarize_trick_points(&self, trick_points: [i32; 2]) -> RoundSummary {
+        let bidders = self.bid_winner as usize % 2;
+        let made_bid = trick_points[bidders] + self.extra_points[bidders] >= self.highest_bid;
+        let scores = [0, 1].map(|team| {
+            let score = trick_points[team] + self.extra_points[team];
+            if team != bidders {
+                self.set_penalty.defender_score(made_bid, score)
+            } else if made_bid {
+                score
+            } else {
+                self.set_penalty
+                    .set_score(self.highest_bid, self.extra_points[team])
+            }
+        });
+
+        RoundSummary {
+            bid_winner: self.bid_winner,
+            highest_bid: self.highest_bid,
+            counters: self.counters,
+            set_penalty: self.set_penalty,
+            outcome: if made_bid {
+                BidOutcome::Made
+            } else {
+                BidOutcome::Set
+            },
+            extra_points: self.extra_points,
+            trick_points,
+            scores,
+        }
+    }
+}
+
+#[test]
+fn test_summarize() {
+    use Rank::*;
+    use Suit::*;
+
+    let mut phase = PlayingPhase {
+        trump: Spades,
+        bid_winner: Player::A,
+        highest_bid: 250,
+        extra_points: [200, 0],
+        counters: CounterScheme::Classic,
+        set_penalty: SetPenalty::Standard,
+        piles: [
+            vec![Card(Hearts, Ace), Card(Hearts, King), Card(Hearts, Nine)],
+            vec![Card(Clubs, Ten), Card(Clubs, Queen), Card(Clubs, Jack)],
+        ],
+        trick: Trick {
+            first_player: Player::A,
+            cards: vec![],
+        },
+    };
+
+    let summary = phase.summarize(Player::B);
+    assert_eq!(summary.trick_points, [15, 25]);
+    assert_eq!(summary.scores, [-250, 25]);
+
+    phase.counters = CounterScheme::AcesTensKings;
+    let summary = phase.summarize(Player::A);
+    assert_eq!(summary.trick_points, [30, 10]);
+    assert_eq!(summary.scores, [-250, 10]);
+
+    phase.counters = CounterScheme::OnePerCounter;
+    phase.highest_bid = 200;
+    let summary = phase.summarize(Player::A);
+    assert_eq!(summary.trick_points, [3, 1]);
+    assert_eq!(summary.scores, [203, 1]);
+    assert_eq!(summary.outcome, BidOutcome::Made);
+}
+
+#[test]
+fn test_set_penalties() {
+    use Rank::*;
+    use Suit::*;
+
+    let mut phase = PlayingPhase {
+        trump: Spades,
+        bid_winner: Player::B,
+        highest_bid: 300,
+        extra_points: [60, 200],
+        counters: CounterScheme::Classic,
+        set_penalty: SetPenalty::Standard,
+        piles: [
+            vec![Card(Hearts, Ace), Card(Hearts, Ten)],
+            vec![Card(Clubs, Ten), Card(Clubs, Ace)],
+        ],
+        trick: Trick {
+            first_player: Player::A,
+            cards: vec![],
+        },
+    };
+
+    let mut scores = |penalty| {
+        phase.set_penalty = penalty;
+        let summary = phase.summarize(Player::A);
+        assert_eq!(summary.set_penalty, penalty);
+        (summary.outcome, summary.scores)
+    };
+    use BidOutcome::*;
+    assert_eq!(scores(SetPenalty::Standard), (Set, [90, -300]));
+    assert_eq!(
+        scores(SetPenalty::DefendersScoreOnlyOnSet),
+        (Set, [90, -300])
+    );
+    assert_eq!(scores(SetPenalty::DoubleSet), (Set, [90, -600]));
+    assert_eq!(scores(SetPenalty::MeldAddedBack), (Set, [90, -100]));
+
+    phase.highest_bid = 220;
+    let mut scores = |penalty| {
+        phase.set_penalty = penalty;
+        let summary = phase.summarize(Player::A);
+        (summary.outcome, summary.scores)
+    };
+    assert_eq!(scores(SetPenalty::Standard), (Made, [90, 220]));
+    assert_eq!(
+        scores(SetPenalty::DefendersScoreOnlyOnSet),
+        (Made, [0, 220])
+    );
+    assert_eq!(scores(SetPenalty::DoubleSet), (Made, [90, 220]));
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Error {
     PlayingNonExtantCard,
     PassingWrongNumberOfCards,
+    PassMustIncludeTrump,
+    NoMarriageInTrump,
+    /// Only a bid winner with no trump they may name can throw the hand in.
+    CannotThrowIn,
+    NotAMisdeal,
     IncorrectAction,
     NotTheCurrentPlayer,
     CardIsNotLegalToPlay,
+    /// More cards are to be passed than a hand holds.
+    PassLargerThanHand,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -825,20 +1381,28 @@ pub enum Action {
     ShowPoints(Vec<usize>),
     Pass(Vec<usize>),
     Play(usize),
+    /// Claims a redeal for a misdealt hand during bidding.
+    Redeal(Player),
+    /// Gives up the hand when the bid winner has no trump they may name.
+    ThrowIn,
 }
 
+/// Stands in for a bid in the encoding of bidding actions to mark a redeal claim.
+const REDEAL_MARKER: u8 = u8::MAX;
+
 impl Action {
-    pub fn encode<'a, 'b, R: Rng>(&self, out: &'a mut BitVec<u8, Lsb0>, game: &'b Game<R>) {
+    pub fn encode<R: Rng>(&self, out: &mut BitVec<u8, Lsb0>, game: &Game<R>) {
         fn cards_to_bitmap(cards: &[usize], n: usize, out: &mut BitVec<u8>) {
-            let res = bits![mut 0; 16];
-            let res = &mut res[..n];
+            let mut res = bitvec![u8, Lsb0; 0; n];
             for card in cards {
                 res.set(*card, true);
             }
 
-            out.extend_from_bitslice(res);
+            out.extend_from_bitslice(&res);
         }
 
+        let hand_size = game.hand.hands[game.hand.current_player as usize].len();
+
         match self {
             Action::Bid(amt) => {
                 let amt = if *amt == 0 {
@@ -848,22 +1412,20 @@ impl Action {
                 };
                 out.extend_from_bitslice(amt.view_bits::<Lsb0>())
             }
+            Action::Redeal(player) => {
+                out.extend_from_bitslice(REDEAL_MARKER.view_bits::<Lsb0>());
+                out.extend_from_bitslice(&(*player as u8).view_bits::<Lsb0>()[..2])
+            }
             Action::Continue(player) => {
                 out.extend_from_bitslice(&(*player as u8).view_bits::<Lsb0>()[..2])
             }
             Action::DeclareSuit(suit) => {
                 out.extend_from_bitslice(&(*suit as u8).view_bits::<Lsb0>()[..2])
             }
+            // A throw-in takes the place of a declaration that can't be made.
+            Action::ThrowIn => out.extend_from_bitslice(bits![u8, Lsb0; 0; 2]),
             Action::ShowPoints(cards) => cards_to_bitmap(cards, 12, out),
-            Action::Pass(cards) => cards_to_bitmap(
-                cards,
-                if matches!(game.hand.phase, Phase::PassingTo { .. }) {
-                    12
-                } else {
-                    16
-                },
-                out,
-            ),
+            Action::Pass(cards) => cards_to_bitmap(cards, hand_size, out),
             Action::Play(card) => {
                 let num_cards = game.hand.hands[game.hand.current_player as usize].len() as u8;
                 let needed_bits = 8 - (num_cards - 1).leading_zeros();
@@ -872,18 +1434,18 @@ impl Action {
         }
     }
 
-    pub fn decode<'a, 'b, R: Rng, T: BitStore>(
+    pub fn decode<'a, R: Rng, T: BitStore>(
         bits: &'a BitSlice<T, Lsb0>,
-        game: &'b Game<R>,
+        game: &Game<R>,
     ) -> Option<(Option<&'a BitSlice<T, Lsb0>>, Self)> {
         fn cards_from_bitmap<T: BitStore>(cards: &BitSlice<T, Lsb0>) -> Vec<usize> {
             cards.iter_ones().collect()
         }
 
-        fn split_at<'a, T: BitStore>(
-            bits: &'a BitSlice<T, Lsb0>,
+        fn split_at<T: BitStore>(
+            bits: &BitSlice<T, Lsb0>,
             mid: usize,
-        ) -> (&'a BitSlice<T, Lsb0>, Option<&'a BitSlice<T, Lsb0>>) {
+        ) -> (&BitSlice<T, Lsb0>, Option<&BitSlice<T, Lsb0>>) {
             if mid == bits.len() {
                 (bits, None)
             } else {
@@ -896,6 +1458,13 @@ impl Action {
             Phase::Bidding { .. } => {
                 let (bid, rest) = split_at(bits, 8);
                 let bid = bid.load_le::<u8>();
+                if bid == REDEAL_MARKER {
+                    let (player, rest) = split_at(rest?, 2);
+                    return Some((
+                        rest,
+                        Action::Redeal((player.load_le::<u8>() as usize).try_into().ok()?),
+                    ));
+                }
                 (
                     rest,
                     Action::Bid(if bid == 0 {
@@ -914,6 +1483,9 @@ impl Action {
             }
             Phase::DeclareTrump { .. } => {
                 let (suit, rest) = split_at(bits, 2);
+                if game.legal_trumps().is_empty() {
+                    return Some((rest, Action::ThrowIn));
+                }
                 (
                     rest,
                     Action::DeclareSuit((suit.load_le::<u8>() as usize).try_into().ok()?),
@@ -923,12 +1495,9 @@ impl Action {
                 let (cards, rest) = split_at(bits, 12);
                 (rest, Action::ShowPoints(cards_from_bitmap(cards)))
             }
-            Phase::PassingTo { .. } => {
-                let (cards, rest) = split_at(bits, 12);
-                (rest, Action::Pass(cards_from_bitmap(cards)))
-            }
-            Phase::PassingBack { .. } => {
-                let (cards, rest) = split_at(bits, 16);
+            Phase::PassingTo { .. } | Phase::PassingBack { .. } => {
+                let hand_size = game.hand.hands[game.hand.current_player as usize].len();
+                let (cards, rest) = split_at(bits, hand_size);
                 (rest, Action::Pass(cards_from_bitmap(cards)))
             }
             Phase::Play(..) => {
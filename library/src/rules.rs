@@ -0,0 +1,196 @@
+use super::{Card, Error, Rank};
+use serde::{Deserialize, Serialize};
+
+/// How many cards each player is dealt.
+const HAND_SIZE: usize = 12;
+
+/// The house rules a game is played with. The default is the variant this
+/// crate has always implemented.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rules {
+    pub counters: CounterScheme,
+    pub passing: PassingRules,
+    /// The bid winner may only name a suit they hold a marriage in. Without
+    /// any marriage, they throw the hand in and are set.
+    pub trump_requires_marriage: bool,
+    pub misdeal: MisdealRules,
+    pub set_penalty: SetPenalty,
+}
+
+impl Rules {
+    /// Checks that a game can be played to the end under the rules.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.passing.count > HAND_SIZE {
+            return Err(Error::PassLargerThanHand);
+        }
+        Ok(())
+    }
+}
+
+/// How a hand is scored when the bidding team falls short of its bid.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SetPenalty {
+    /// The bidders lose their bid and the defenders keep what they took.
+    #[default]
+    Standard,
+    /// As [`SetPenalty::Standard`], but the defenders only score when the
+    /// bidders are set.
+    DefendersScoreOnlyOnSet,
+    /// The bidders lose twice their bid.
+    DoubleSet,
+    /// The bidders lose their bid, but get their meld added back.
+    MeldAddedBack,
+}
+
+impl SetPenalty {
+    /// What the bidding team scores when it is set.
+    pub fn set_score(self, highest_bid: i32, extra_points: i32) -> i32 {
+        match self {
+            SetPenalty::Standard | SetPenalty::DefendersScoreOnlyOnSet => -highest_bid,
+            SetPenalty::DoubleSet => -2 * highest_bid,
+            SetPenalty::MeldAddedBack => extra_points - highest_bid,
+        }
+    }
+
+    /// What the defending team scores, given what they took.
+    pub fn defender_score(self, made_bid: bool, score: i32) -> i32 {
+        match self {
+            SetPenalty::DefendersScoreOnlyOnSet if made_bid => 0,
+            _ => score,
+        }
+    }
+}
+
+/// Which dealt hands entitle their holder to claim a redeal during bidding.
+/// By default no hand does.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MisdealRules {
+    /// A hand with at least this many nines is a misdeal.
+    pub nines: Option<usize>,
+    /// A hand without a single counter is a misdeal.
+    pub no_counters: bool,
+}
+
+impl MisdealRules {
+    pub fn is_misdeal(&self, hand: &[Card], counters: CounterScheme) -> bool {
+        let too_many_nines = self
+            .nines
+            .is_some_and(|nines| hand.iter().filter(|c| c.1 == Rank::Nine).count() >= nines);
+        let no_counters = self.no_counters && hand.iter().all(|c| counters.points(c.1) == 0);
+        too_many_nines || no_counters
+    }
+}
+
+/// How cards change hands between the bid winner and their partner after
+/// trump is declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PassingRules {
+    /// How many cards go to the bid winner and then back. Zero skips
+    /// passing entirely.
+    pub count: usize,
+    /// The partner's pass has to include trump if they hold any.
+    pub partner_passes_trump: bool,
+}
+
+impl Default for PassingRules {
+    fn default() -> Self {
+        Self {
+            count: 4,
+            partner_passes_trump: false,
+        }
+    }
+}
+
+/// What the cards taken in tricks are worth.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CounterScheme {
+    /// Aces and tens count 10, kings and queens 5, and the last trick 10.
+    #[default]
+    Classic,
+    /// Aces, tens and kings count 10, and the last trick 10.
+    AcesTensKings,
+    /// Aces, tens and kings count 1, and the last trick 1, for 25 in total.
+    /// Meld is not scaled down to match.
+    OnePerCounter,
+}
+
+impl CounterScheme {
+    pub fn points(self, rank: Rank) -> i32 {
+        match (self, rank) {
+            (CounterScheme::Classic, Rank::Ace | Rank::Ten) => 10,
+            (CounterScheme::Classic, Rank::King | Rank::Queen) => 5,
+            (CounterScheme::AcesTensKings, Rank::Ace | Rank::Ten | Rank::King) => 10,
+            (CounterScheme::OnePerCounter, Rank::Ace | Rank::Ten | Rank::King) => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn last_trick(self) -> i32 {
+        match self {
+            CounterScheme::Classic | CounterScheme::AcesTensKings => 10,
+            CounterScheme::OnePerCounter => 1,
+        }
+    }
+
+    /// Every point that can be taken in tricks in a single hand.
+    pub fn total(self) -> i32 {
+        enum_iterator::all::<Rank>()
+            .map(|rank| self.points(rank) * 8)
+            .sum::<i32>()
+            + self.last_trick()
+    }
+}
+
+#[test]
+fn test_is_misdeal() {
+    use super::Suit::*;
+    use Rank::*;
+
+    let rules = MisdealRules {
+        nines: Some(5),
+        no_counters: true,
+    };
+    let nines = [
+        Card(Hearts, Nine),
+        Card(Hearts, Nine),
+        Card(Spades, Nine),
+        Card(Clubs, Nine),
+        Card(Diamonds, Nine),
+        Card(Hearts, Ace),
+    ];
+    assert!(rules.is_misdeal(&nines, CounterScheme::Classic));
+    assert!(!rules.is_misdeal(&nines[1..], CounterScheme::Classic));
+
+    let queens = [Card(Hearts, Queen), Card(Spades, Jack), Card(Clubs, Nine)];
+    assert!(!rules.is_misdeal(&queens, CounterScheme::Classic));
+    assert!(rules.is_misdeal(&queens, CounterScheme::AcesTensKings));
+    assert!(!MisdealRules::default().is_misdeal(&queens, CounterScheme::AcesTensKings));
+}
+
+#[test]
+fn test_counter_totals() {
+    assert_eq!(CounterScheme::Classic.total(), 250);
+    assert_eq!(CounterScheme::AcesTensKings.total(), 250);
+    assert_eq!(CounterScheme::OnePerCounter.total(), 25);
+}
+
+#[test]
+fn test_validate() {
+    use crate::Game;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let passing = |count| Rules {
+        passing: PassingRules {
+            count,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert_eq!(passing(12).validate(), Ok(()));
+    assert_eq!(passing(13).validate(), Err(Error::PassLargerThanHand));
+    assert!(Game::with_rules(StdRng::seed_from_u64(0), passing(13)).is_err());
+}
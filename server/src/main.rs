@@ -2,18 +2,25 @@ use actix_cors::Cors;
 use actix_web::{get, post, put, web, App, HttpResponse, HttpServer, Responder};
 use base64::{engine::general_purpose::STANDARD, Engine};
 use bitvec::prelude::*;
-use pinochle::ai::Bot;
-use pinochle::{Action, Error, Game, Phase, Player};
+use pinochle::ai::{Bot, BotConfig, BotProfile, PlayerView};
+use pinochle::rules::Rules;
+use pinochle::{Action, Game, Phase, Player, RoundSummary, Suit};
 use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Mutex;
+use std::time::Duration;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct GameState {
     player_names: [String; 4],
     seed: [u8; 32],
     actions: Vec<Action>,
+    #[serde(default)]
+    rules: Rules,
+    /// How the bot plays in each seat, if asked to.
+    #[serde(default)]
+    bots: [BotProfile; 4],
 }
 
 impl GameState {
@@ -27,11 +34,14 @@ impl GameState {
             ],
             seed: thread_rng().gen(),
             actions: Default::default(),
+            rules: Default::default(),
+            bots: Default::default(),
         }
     }
 
     fn game(&self) -> Game<StdRng> {
-        let mut game = Game::new(StdRng::from_seed(self.seed));
+        let mut game = Game::with_rules(StdRng::from_seed(self.seed), self.rules)
+            .expect("the rules were checked when the game was created");
         for action in &self.actions {
             game.act(action.clone()).unwrap();
         }
@@ -39,7 +49,7 @@ impl GameState {
     }
 
     fn to_bytes(&self) -> Vec<u8> {
-        let mut actions = bitvec![u8, Lsb0; 0; 8];
+        let mut actions = BitVec::<u8, Lsb0>::from_element(2);
         actions.extend_from_bitslice(self.player_names[0].as_bits::<Lsb0>());
         actions.extend_from_bitslice(bits!(0; 8));
         actions.extend_from_bitslice(self.player_names[1].as_bits::<Lsb0>());
@@ -48,9 +58,16 @@ impl GameState {
         actions.extend_from_bitslice(bits!(0; 8));
         actions.extend_from_bitslice(self.player_names[3].as_bits::<Lsb0>());
         actions.extend_from_bitslice(bits!(0; 8));
+        let rules = serde_json::to_string(&self.rules).unwrap();
+        actions.extend_from_bitslice(rules.as_bits::<Lsb0>());
+        actions.extend_from_bitslice(bits!(0; 8));
+        let bots = serde_json::to_string(&self.bots).unwrap();
+        actions.extend_from_bitslice(bots.as_bits::<Lsb0>());
+        actions.extend_from_bitslice(bits!(0; 8));
         actions.extend_from_bitslice(self.seed.as_bits::<Lsb0>());
         actions.extend_from_bitslice((self.actions.len() as u32).to_le_bytes().as_bits::<Lsb0>());
-        let mut game = Game::new(StdRng::from_seed(self.seed));
+        let mut game = Game::with_rules(StdRng::from_seed(self.seed), self.rules)
+            .expect("the rules were checked when the game was created");
         for action in &self.actions {
             action.encode(&mut actions, &game);
             game.act(action.clone()).unwrap();
@@ -66,13 +83,25 @@ impl GameState {
                 &bytes[(idx + 1)..],
             ))
         }
-        let _version = bytes[0];
+        let version = bytes[0];
         let bytes = &bytes[1..];
 
         let (a, bytes) = get_str(bytes)?;
         let (b, bytes) = get_str(bytes)?;
         let (c, bytes) = get_str(bytes)?;
         let (d, bytes) = get_str(bytes)?;
+        let (rules, bytes) = if version >= 1 {
+            let (rules, bytes) = get_str(bytes)?;
+            (serde_json::from_str(rules).ok()?, bytes)
+        } else {
+            (Rules::default(), bytes)
+        };
+        let (bots, bytes) = if version >= 2 {
+            let (bots, bytes) = get_str(bytes)?;
+            (serde_json::from_str(bots).ok()?, bytes)
+        } else {
+            (Default::default(), bytes)
+        };
 
         let seed = bytes.get(0..32)?.try_into().ok()?;
         let bytes = &bytes[32..];
@@ -80,7 +109,7 @@ impl GameState {
         let length = u32::from_le_bytes(length.try_into().ok()?);
         let bytes = &bytes[4..];
         let mut actions = vec![];
-        let mut game = Game::new(StdRng::from_seed(seed));
+        let mut game = Game::with_rules(StdRng::from_seed(seed), rules).ok()?;
         let mut bits = bytes.as_bits::<Lsb0>();
         while actions.len() < length as usize {
             let (new_bits, action) = Action::decode(bits, &game)?;
@@ -94,15 +123,32 @@ impl GameState {
         }
         Some(Self {
             player_names: [
-                if a.len() == 0 { "A" } else { a }.to_owned(),
-                if b.len() == 0 { "B" } else { b }.to_owned(),
-                if c.len() == 0 { "C" } else { c }.to_owned(),
-                if d.len() == 0 { "D" } else { d }.to_owned(),
+                if a.is_empty() { "A" } else { a }.to_owned(),
+                if b.is_empty() { "B" } else { b }.to_owned(),
+                if c.is_empty() { "C" } else { c }.to_owned(),
+                if d.is_empty() { "D" } else { d }.to_owned(),
             ],
             actions,
             seed,
+            rules,
+            bots,
         })
     }
+
+    /// How the bot in `player`'s seat plays, within the server's limits.
+    fn bot_config(&self, player: Player, limits: BotLimits) -> BotConfig {
+        let mut config = self.bots[player as usize].config();
+        limits.apply(&mut config);
+        config
+    }
+
+    /// Where the bots' choices are drawn from. It's derived from the game's
+    /// seed, so replaying a game reproduces what its bots chose.
+    fn bot_rng(&self) -> StdRng {
+        StdRng::seed_from_u64(
+            u64::from_le_bytes(self.seed[..8].try_into().unwrap()) ^ self.actions.len() as u64,
+        )
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -112,6 +158,12 @@ pub struct GameInfo {
     current_player: Player,
     phase: Phase,
     scores: [i32; 2],
+    rounds: Vec<RoundSummary>,
+    rules: Rules,
+    legal_trumps: Vec<Suit>,
+    /// Whether the player the info is for may claim a misdeal. Always false
+    /// when it's for no one in particular.
+    can_claim_misdeal: bool,
 }
 
 impl GameInfo {
@@ -122,6 +174,17 @@ impl GameInfo {
             current_player: game.current_player(),
             phase: game.phase().clone(),
             scores: game.scores(),
+            rounds: game.rounds().to_vec(),
+            rules: *game.rules(),
+            legal_trumps: game.legal_trumps(),
+            can_claim_misdeal: false,
+        }
+    }
+
+    fn for_player<R: Rng>(player_names: &[String; 4], game: &Game<R>, player: Player) -> Self {
+        GameInfo {
+            can_claim_misdeal: game.can_claim_misdeal(player),
+            ..Self::from_game(player_names, game)
         }
     }
 }
@@ -134,6 +197,45 @@ impl<R: Rng> From<&Game<R>> for GameInfo {
 
 struct AppState {
     games: Mutex<HashMap<String, GameState>>,
+    bot_limits: BotLimits,
+}
+
+/// Server-wide caps on the bots' search. Each seat's difficulty keeps its own
+/// budget where that's already within them.
+#[derive(Debug, Default, Clone, Copy)]
+struct BotLimits {
+    max_iterations: Option<usize>,
+    time_limit: Option<Duration>,
+}
+
+impl BotLimits {
+    /// Reads the limits from `BOT_MAX_ITERATIONS` and `BOT_TIME_LIMIT_MS`.
+    fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok()?.parse().ok()
+        }
+        Self {
+            max_iterations: var("BOT_MAX_ITERATIONS"),
+            time_limit: var("BOT_TIME_LIMIT_MS").map(Duration::from_millis),
+        }
+    }
+
+    fn apply(&self, config: &mut BotConfig) {
+        if let Some(max_iterations) = self.max_iterations {
+            config.max_iterations = Some(
+                config
+                    .max_iterations
+                    .map_or(max_iterations, |own| own.min(max_iterations)),
+            );
+        }
+        if let Some(time_limit) = self.time_limit {
+            config.time_limit = Some(
+                config
+                    .time_limit
+                    .map_or(time_limit, |own| own.min(time_limit)),
+            );
+        }
+    }
 }
 
 #[get("/game")]
@@ -155,6 +257,21 @@ async fn get_game(game: web::Path<String>, data: web::Data<AppState>) -> impl Re
     }
 }
 
+#[get("/game/{game}/{player}/info")]
+async fn get_player_game(
+    game: web::Path<(String, Player)>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let games = data.games.lock().unwrap();
+    let (name, player) = game.into_inner();
+    if let Some(game) = games.get(&name) {
+        let info = GameInfo::for_player(&game.player_names, &game.game(), player);
+        HttpResponse::Ok().json(&info)
+    } else {
+        HttpResponse::NotFound().body("")
+    }
+}
+
 #[get("/game/{game}/full")]
 async fn get_full_game(game: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
     let games = data.games.lock().unwrap();
@@ -202,57 +319,81 @@ async fn get_hand(game: web::Path<(String, Player)>, data: web::Data<AppState>)
     let games = data.games.lock().unwrap();
     let (name, player) = game.into_inner();
     if let Some(game) = games.get(&name) {
-        HttpResponse::Ok().json(&game.game().player_hand(player))
+        HttpResponse::Ok().json(game.game().player_hand(player))
     } else {
         HttpResponse::NotFound().body("")
     }
 }
 
+/// Scores `player`'s cards, which only they may ask for, and only on their
+/// turn. The search runs without holding the games lock.
+#[get("/game/{game}/{player}/analysis")]
+async fn get_analysis(
+    game: web::Path<(String, Player)>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let (name, player) = game.into_inner();
+    let (bot, mut rng) = {
+        let games = data.games.lock().unwrap();
+        let Some(game_init) = games.get(&name) else {
+            return HttpResponse::NotFound().body("");
+        };
+        let game = game_init.game();
+        if player != game.current_player() {
+            return HttpResponse::BadRequest().body("NotTheCurrentPlayer");
+        }
+        let view = PlayerView::new(&game, player);
+        let bot = match Bot::from_view(&view, game_init.bot_config(player, data.bot_limits)) {
+            Some(Ok(bot)) => bot,
+            Some(Err(inconsistency)) => {
+                return HttpResponse::InternalServerError().body(inconsistency.to_string())
+            }
+            None => {
+                return HttpResponse::BadRequest()
+                    .body("Cards can only be analyzed during the play")
+            }
+        };
+        (bot, game_init.bot_rng())
+    };
+    match bot.analyze_with_rng(&mut rng) {
+        Ok(analysis) => HttpResponse::Ok().json(analysis),
+        Err(inconsistency) => HttpResponse::InternalServerError().body(inconsistency.to_string()),
+    }
+}
+
+/// Has the bot in the current player's seat act. Like the analysis, the bot
+/// decides without holding the games lock, so the game is checked to have
+/// stayed put before its action is taken.
 #[post("/game/{game}/trigger-bot")]
 async fn trigger_bot(game: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
-    let mut games = data.games.lock().unwrap();
     let name = game.into_inner();
-    if let Some(game_init) = games.get_mut(&name) {
-        let bot_player = game_init.game().current_player();
-
-        let mut game = Game::new(StdRng::from_seed(game_init.seed));
-
-        let mut bot: Option<Bot> = None;
-
-        for action in &game_init.actions {
-            if let Phase::Play(playing_phase) = game.phase() {
-                if bot.is_none() {
-                    bot = Some(Bot::new(
-                        bot_player,
-                        game.player_hand(bot_player),
-                        playing_phase.clone(),
-                    ));
-                }
-                if let Action::Play(card) = action {
-                    if let Some(bot) = &mut bot {
-                        let player_hand = game.player_hand(game.current_player());
-
-                        bot.update(
-                            game.current_player(),
-                            player_hand[*card],
-                            playing_phase.trump,
-                            &playing_phase.trick.cards,
-                        );
-                    }
-                }
-            }
-            game.act(action.clone()).unwrap();
-        }
-        let chosen_card = bot.unwrap().get_move();
+    let (mut game, view, config, mut rng, position) = {
+        let games = data.games.lock().unwrap();
+        let Some(game_init) = games.get(&name) else {
+            return HttpResponse::Ok().body("");
+        };
+        let game = game_init.game();
+        let bot_player = game.current_player();
+        let config = game_init.bot_config(bot_player, data.bot_limits);
+        let view = PlayerView::new(&game, bot_player);
+        let position = (game_init.seed, game_init.actions.len());
+        (game, view, config, game_init.bot_rng(), position)
+    };
+    let Some(chosen_action) = Bot::decide(&view, &config, &mut rng) else {
+        return HttpResponse::BadRequest().body("The bot can't act in this phase");
+    };
 
-        let chosen_action = game
-            .player_hand(bot_player)
-            .iter()
-            .position(|x| *x == chosen_card)
-            .unwrap();
-
-        game_init.actions.push(Action::Play(chosen_action));
+    let mut games = data.games.lock().unwrap();
+    let Some(game_init) = games.get_mut(&name) else {
+        return HttpResponse::NotFound().body("");
+    };
+    if (game_init.seed, game_init.actions.len()) != position {
+        return HttpResponse::Conflict().body("The game moved on while the bot was deciding");
     }
+    if let Err(err) = game.act(chosen_action.clone()) {
+        return HttpResponse::InternalServerError().body(format!("{err:?}"));
+    }
+    game_init.actions.push(chosen_action);
     HttpResponse::Ok().body("")
 }
 
@@ -288,7 +429,7 @@ async fn act(
     if let Some(game_state) = games.get_mut(&name) {
         let action = info.0.clone();
         let mut game = game_state.game();
-        if let Action::Continue(action_player) = action {
+        if let Action::Continue(action_player) | Action::Redeal(action_player) = action {
             if action_player != player {
                 return HttpResponse::BadRequest().body("NotTheCurrentPlayer");
             }
@@ -297,21 +438,7 @@ async fn act(
         }
 
         if let Some(err) = game.act(action).err() {
-            match err {
-                Error::PlayingNonExtantCard => {
-                    HttpResponse::BadRequest().body("PlayingNonExtantCard")
-                }
-                Error::PassingWrongNumberOfCards => {
-                    HttpResponse::BadRequest().body("PassingWrongNumberOfCards")
-                }
-                Error::NotTheCurrentPlayer => {
-                    HttpResponse::BadRequest().body("NotTheCurrentPlayer")
-                }
-                Error::CardIsNotLegalToPlay => {
-                    HttpResponse::BadRequest().body("CardIsNotLegalToPlay")
-                }
-                Error::IncorrectAction => HttpResponse::BadRequest().body("IncorrectAction"),
-            }
+            HttpResponse::BadRequest().body(format!("{err:?}"))
         } else {
             game_state.actions.push(info.0);
             HttpResponse::Ok().body("")
@@ -333,8 +460,11 @@ async fn create_with(
     info: web::Json<GameState>,
     data: web::Data<AppState>,
 ) -> impl Responder {
+    if let Err(err) = info.rules.validate() {
+        return HttpResponse::NotAcceptable().body(format!("{err:?}"));
+    }
     create(game, info.into_inner(), data);
-    HttpResponse::Ok()
+    HttpResponse::Ok().body("")
 }
 
 #[post("/game/{game}")]
@@ -347,6 +477,7 @@ async fn create_without(game: web::Path<String>, data: web::Data<AppState>) -> i
 async fn main() -> std::io::Result<()> {
     let shared = web::Data::new(AppState {
         games: Mutex::default(),
+        bot_limits: BotLimits::from_env(),
     });
 
     HttpServer::new(move || {
@@ -361,14 +492,19 @@ async fn main() -> std::io::Result<()> {
             .service(set_name)
             .service(get_full_game)
             .service(get_game)
+            .service(get_player_game)
             .service(get_games)
             .service(get_hand)
             .service(get_b64_game)
             .service(create_with_b64)
             .service(create_without)
             .service(trigger_bot)
+            .service(get_analysis)
             .service(create_with)
-            .service(actix_files::Files::new("/game/{name}/{player}", "./www/build").index_file("index.html"))
+            .service(
+                actix_files::Files::new("/game/{name}/{player}", "./www/build")
+                    .index_file("index.html"),
+            )
             .service(actix_files::Files::new("/", "./www/build").index_file("index.html"))
     })
     .bind(("0.0.0.0", 8080))?
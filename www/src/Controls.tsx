@@ -18,8 +18,9 @@ function Controls(props: {
     }
 
     const passTo = (target: Player) => {
-        if (props.selectedCards.size < 4) {
-            return (<div>Select 4 cards to pass to {target}</div>)
+        const count = props.gameInfo.rules.passing.count;
+        if (props.selectedCards.size < count) {
+            return (<div>Select {count} cards to pass to {target}</div>)
         } else {
             return (<input
                 type="button"
@@ -46,19 +47,27 @@ function Controls(props: {
     const subElements = {
         Bidding: (phase: Phase['Bidding']) => {
             const ref = useRef<HTMLInputElement | null>(null);
-            if (!amCurrentPlayer) return waitingMessage;
+            const redeal = props.gameInfo.can_claim_misdeal
+                ? <input type="button" value="Claim misdeal" onClick={() => onAct({ "Redeal": props.player })} />
+                : null;
+            if (!amCurrentPlayer) return (<div>{waitingMessage}{redeal}</div>);
             return (<form onSubmit={(e) => { e.preventDefault(); onAct({ "Bid": Number(ref.current?.value) }) }}>
-                <input type="number" ref={ref} /> <input type="submit" value="Bid" />
+                <input type="number" ref={ref} /> <input type="submit" value="Bid" /> {redeal}
             </form>)
         },
         DeclareTrump: (phase: Phase['DeclareTrump']) => {
             if (!amCurrentPlayer) return waitingMessage;
+            const legal = props.gameInfo.legal_trumps;
+            if (legal.length === 0) {
+                return (<div>
+                    You hold no marriage to name trump in:
+                    <input type="button" value="Throw in" onClick={() => onAct("ThrowIn")} />
+                </div>)
+            }
+            const suits: Suit[] = ["Diamonds", "Clubs", "Hearts", "Spades"];
             return (<div>
                 Pick a trump suit:
-                <input type="button" value="Diamonds" onClick={() => onAct({ "DeclareSuit": "Diamonds" })} />
-                <input type="button" value="Clubs" onClick={() => onAct({ "DeclareSuit": "Clubs" })} />
-                <input type="button" value="Hearts" onClick={() => onAct({ "DeclareSuit": "Hearts" })} />
-                <input type="button" value="Spades" onClick={() => onAct({ "DeclareSuit": "Spades" })} />
+                {suits.map(suit => <input type="button" value={suit} disabled={!legal.includes(suit)} onClick={() => onAct({ "DeclareSuit": suit })} />)}
             </div>)
         },
         PassingTo: (phase: Phase['PassingTo']) => {
@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react"
-import { Card, GameInfo, Client, selectionMax, Phase, Player, FullState, playerToIndex } from "./model";
+import { Card, CardAnalysis, GameInfo, Client, selectionMax, Phase, Player, FullState, playerToIndex } from "./model";
+import CardView from "./Card";
 import Controls from "./Controls";
 import DevTools from "./DevTools";
 import Hand from "./Hand"
@@ -28,6 +29,7 @@ export default function PlayGame({
     const [myHand, setMyHand] = useState<Card[]>([]);
     const [gameData, setGameData] = useState<GameInfo | undefined>(undefined);
     const [trackFullState, setTrackFullState] = useState<boolean>(false);
+    const [hint, setHint] = useState<CardAnalysis | undefined>(undefined);
 
     const setFullState = async (newPlayerNames: string, newSeed: string, newActions: string) => {
         try {
@@ -56,7 +58,7 @@ export default function PlayGame({
 
     const refresh = async (game: string | undefined = gameName) => {
         if (game) {
-            const gameInfo = await client.getGameData(game);
+            const gameInfo = await client.getGameData(game, myPlayer);
             setGameData(gameInfo);
             if (myPlayer) {
                 const hand = await client.getHand(game, myPlayer);
@@ -85,13 +87,25 @@ export default function PlayGame({
         if (gameName && gameData && myPlayer) {
             await client.act(gameName, myPlayer, action);
             setSelectedCards(new Set());
+            setHint(undefined);
             await refresh();
         }
     }
 
+    const askForHint = async () => {
+        const [best] = await client.getAnalysis(gameName, myPlayer);
+        if (best) {
+            setHint(best);
+            const index = myHand.findIndex(card => card.suit === best.card.suit && card.rank === best.card.rank);
+            if (index >= 0) {
+                setSelectedCards(new Set([index]));
+            }
+        }
+    }
+
     const selectCards = (cards: Set<number>) => {
-        if (phase) {
-            const max = selectionMax(phase);
+        if (phase && gameData) {
+            const max = selectionMax(phase, gameData.rules);
             if (cards.size <= max) {
                 setSelectedCards(cards);
             } else if (max === 1) {
@@ -119,6 +133,8 @@ export default function PlayGame({
         return <div>loading...</div>;
     }
 
+    const canAskForHint = phase === "Play" && gameData.current_player === myPlayer;
+
     return (
         <div className="App">
             <div>
@@ -139,6 +155,10 @@ export default function PlayGame({
                 />
                 {trump && <div id="trump-suit"><span className={`suit-${trump}`}>{trumpSymbols[trump]}</span></div>}
                 <Controls gameInfo={gameData} player={myPlayer} onAct={gotAction} selectedCards={selectedCards} />
+                {canAskForHint && <div id="hint">
+                    <button onClick={askForHint}>Hint</button>
+                    {hint && <div>The bot would play <CardView card={hint.card} />, expecting {hint.expected_score.toFixed(1)} points.</div>}
+                </div>}
             </div>
             <Rules />
             {
@@ -16,12 +16,12 @@ export const prevPlayer = (player: Player): Player => nextPlayer(nextPlayer(next
 
 export const partner = (player: Player) => nextPlayer(nextPlayer(player));
 
-export const selectionMax = (phase: keyof Phase) => {
+export const selectionMax = (phase: keyof Phase, rules: Rules) => {
     switch (phase) {
         case "Bidding": return 0;
         case "DeclareTrump": return 0;
-        case "PassingBack": return 4;
-        case "PassingTo": return 4;
+        case "PassingBack": return rules.passing.count;
+        case "PassingTo": return rules.passing.count;
         case "RevealingCards": return 12;
         case "ReviewingRevealedCards": return 0;
         case "Play": return 1;
@@ -67,6 +67,8 @@ export type Phase = {
         bid_winner: Player,
         highest_bid: number,
         extra_points: [number, number],
+        counters: CounterScheme,
+        set_penalty: SetPenalty,
         piles: [[Suit, Rank][], [Suit, Rank][]],
         trick: {
             "first_player": Player,
@@ -74,6 +76,44 @@ export type Phase = {
         },
     },
 }
+export type CounterScheme = "Classic" | "AcesTensKings" | "OnePerCounter"
+
+export type SetPenalty = "Standard" | "DefendersScoreOnlyOnSet" | "DoubleSet" | "MeldAddedBack"
+
+export type Rules = {
+    counters: CounterScheme,
+    passing: {
+        count: number,
+        partner_passes_trump: boolean,
+    },
+    trump_requires_marriage: boolean,
+    misdeal: {
+        nines: number | null,
+        no_counters: boolean,
+    },
+    set_penalty: SetPenalty,
+}
+
+export type RoundSummary = {
+    bid_winner: Player,
+    highest_bid: number,
+    counters: CounterScheme,
+    set_penalty: SetPenalty,
+    outcome: "Made" | "Set" | "ThrownIn",
+    extra_points: [number, number],
+    trick_points: [number, number],
+    scores: [number, number],
+}
+
+export type CardAnalysis = {
+    card: Card,
+    expected_score: number,
+    variance: number,
+    samples: number,
+    make_probability: number,
+    utility: number,
+}
+
 export type GameInfo = {
     "player_names": [string, string, string, string],
     "first_bidder": Player,
@@ -82,7 +122,11 @@ export type GameInfo = {
     "scores": [
         number,
         number
-    ]
+    ],
+    "rounds": RoundSummary[],
+    "rules": Rules,
+    "legal_trumps": Suit[],
+    "can_claim_misdeal": boolean,
 }
 
 export function playerToIndex(player: Player): number {
@@ -94,7 +138,18 @@ export const toCard = ([suit, rank]: [Suit, Rank]): Card => {
     return { rank, suit };
 }
 
-export type FullState = { "seed": number[], "actions": unknown[], "player_names": [string, string, string, string] };
+export type Difficulty = "Easy" | "Medium" | "Hard" | "Expert";
+
+export type BiddingStyle = "Conservative" | "Balanced" | "Aggressive";
+
+export type BotProfile = { difficulty: Difficulty, bidding: BiddingStyle };
+
+export type FullState = {
+    "seed": number[],
+    "actions": unknown[],
+    "player_names": [string, string, string, string],
+    "bots"?: [BotProfile, BotProfile, BotProfile, BotProfile],
+};
 
 const baseUrl = window.location.port === "3000" ? "http://localhost:8080" : window.location.port === "3001" ? "https://pinochle.sheyne.com" : "";
 
@@ -130,8 +185,9 @@ export class Client {
         return await result.text()
     }
 
-    async getGameData(game: string): Promise<GameInfo> {
-        return await (await fetch(`${baseUrl}/game/${game}`)).json() as GameInfo
+    async getGameData(game: string, player?: Player): Promise<GameInfo> {
+        const url = player ? `${baseUrl}/game/${game}/${player}/info` : `${baseUrl}/game/${game}`;
+        return await (await fetch(url)).json() as GameInfo
     }
 
     async getHand(game: string, player: Player): Promise<Card[]> {
@@ -139,6 +195,12 @@ export class Client {
         return (await myHandResponse.json() as [Suit, Rank][]).map(toCard);
     }
 
+    async getAnalysis(game: string, player: Player): Promise<CardAnalysis[]> {
+        const analysis = await fetch(`${baseUrl}/game/${game}/${player}/analysis`);
+        type Raw = Omit<CardAnalysis, "card"> & { card: [Suit, Rank] };
+        return (await analysis.json() as Raw[]).map(entry => ({ ...entry, card: toCard(entry.card) }));
+    }
+
     async getFullState(game: string): Promise<FullState> {
         const full = await fetch(`${baseUrl}/game/${game}/full`);
         return await full.json();